@@ -120,9 +120,14 @@ impl<'a, 'b> ast::VisitorMap<ast::ParsedOperand<SpirvWord>, TypedOperand, Transl
     ) -> Result<TypedOperand, TranslateError> {
         Ok(match op {
             ast::ParsedOperand::Reg(reg) => TypedOperand::Reg(reg),
-            ast::ParsedOperand::RegOffset(reg, offset) => TypedOperand::RegOffset(reg, offset),
+            ast::ParsedOperand::RegOffset(reg, offset)
+            | ast::ParsedOperand::SymbolOffset(reg, offset) => TypedOperand::RegOffset(reg, offset),
             ast::ParsedOperand::Imm(x) => TypedOperand::Imm(x),
-            ast::ParsedOperand::VecMember(vec, idx) => TypedOperand::VecMember(vec, idx),
+            ast::ParsedOperand::VecMember(vec, idx)
+            | ast::ParsedOperand::SpecialRegisterDimension(vec, idx) => {
+                TypedOperand::VecMember(vec, idx)
+            }
+            ast::ParsedOperand::Generic(_) => return Err(TranslateError::Todo),
             ast::ParsedOperand::VecPack(vec) => {
                 let (type_, space) = type_space.ok_or_else(|| error_mismatched_type())?;
                 TypedOperand::Reg(self.convert_vector(
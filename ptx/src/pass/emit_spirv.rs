@@ -967,6 +967,13 @@ fn emit_function_body_ops<'input>(
             }
             Statement::Instruction(inst) => match inst {
                 ast::Instruction::PrmtSlow { .. } | ast::Instruction::Trap { .. } => todo!(),
+                ast::Instruction::Video { .. }
+                | ast::Instruction::CvtPack { .. }
+                | ast::Instruction::ShflSync { .. }
+                | ast::Instruction::BarArrive { .. }
+                | ast::Instruction::Lop3 { .. }
+                | ast::Instruction::Shf { .. }
+                | ast::Instruction::Sad { .. } => return Err(TranslateError::Todo),
                 ast::Instruction::Call { data, arguments } => {
                     let (result_type, result_id) =
                         match (&*data.return_arguments, &*arguments.return_arguments) {
@@ -1128,10 +1135,15 @@ fn emit_function_body_ops<'input>(
                         type_,
                         control,
                         saturate,
+                        carry_in,
+                        carry_out,
                     } => {
                         if *saturate {
                             todo!()
                         }
+                        if *carry_in || *carry_out {
+                            return Err(TranslateError::Todo);
+                        }
                         if type_.kind() == ast::ScalarKind::Signed {
                             emit_mad_sint(builder, map, opencl, *type_, *control, arguments)?
                         } else {
@@ -2140,6 +2152,7 @@ fn emit_cvt(
             };
             emit_implicit_conversion(builder, map, &cv)?;
         }
+        ptx_parser::CvtMode::Truncate if dets.saturate => return Err(TranslateError::Todo),
         ptx_parser::CvtMode::ZeroExtend
         | ptx_parser::CvtMode::Truncate
         | ptx_parser::CvtMode::Bitcast => {
@@ -2169,7 +2182,11 @@ fn emit_cvt(
         ptx_parser::CvtMode::FPTruncate {
             rounding,
             flush_to_zero,
+            relu,
         } => {
+            if relu {
+                return Err(TranslateError::Todo);
+            }
             let result_type = map.get_or_add(builder, SpirvType::from(dets.to));
             builder.f_convert(result_type.0, Some(arg.dst.0), arg.src.0)?;
             emit_rounding_decoration(builder, arg.dst, Some(rounding));
@@ -2254,6 +2271,7 @@ fn emit_cvt(
             builder.convert_u_to_f(result_type.0, Some(arg.dst.0), arg.src.0)?;
             emit_rounding_decoration(builder, arg.dst, Some(rounding));
         }
+        ptx_parser::CvtMode::Tf32 { .. } => return Err(TranslateError::Todo),
     }
     Ok(())
 }
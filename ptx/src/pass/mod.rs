@@ -89,6 +89,7 @@ fn translate_directive<'input, 'a>(
         ast::Directive::Method(linkage, f) => {
             translate_function(id_defs, ptx_impl_imports, linkage, f)?.map(Directive::Method)
         }
+        ast::Directive::DwarfLine(_) => None,
     })
 }
 
@@ -1550,7 +1551,8 @@ fn flush_to_zero(this: &ast::Instruction<SpirvWord>) -> Option<(bool, u8)> {
                         | ast::CvtMode::SaturateUnsignedToSigned
                         | ast::CvtMode::SaturateSignedToUnsigned
                         | ast::CvtMode::FPFromSigned(_)
-                        | ast::CvtMode::FPFromUnsigned(_),
+                        | ast::CvtMode::FPFromUnsigned(_)
+                        | ast::CvtMode::Tf32 { .. },
                     ..
                 },
             ..
@@ -1651,6 +1653,13 @@ fn flush_to_zero(this: &ast::Instruction<SpirvWord>) -> Option<(bool, u8)> {
         }
         ptx_parser::Instruction::PrmtSlow { .. } => None,
         ptx_parser::Instruction::Trap {} => None,
+        ptx_parser::Instruction::Video { .. } => None,
+        ptx_parser::Instruction::CvtPack { .. } => None,
+        ptx_parser::Instruction::ShflSync { .. } => None,
+        ptx_parser::Instruction::Lop3 { .. } => None,
+        ptx_parser::Instruction::Shf { .. } => None,
+        ptx_parser::Instruction::Sad { .. } => None,
+        ptx_parser::Instruction::BarArrive { .. } => None,
     }
 }
 
@@ -75,6 +75,8 @@ fn expand_map_variables<'a, 'b>(
                 }
             }
         }
+        // Pragmas are hints, we don't act on any of them
+        ast::Statement::Pragma(_) => {}
     };
     Ok(())
 }
@@ -42,7 +42,7 @@ fn jump_target<T: ast::Operand<Ident = SpirvWord>>(
     this: &ast::Instruction<T>,
 ) -> Option<SpirvWord> {
     match this {
-        ast::Instruction::Bra { arguments } => Some(arguments.src),
+        ast::Instruction::Bra { arguments, .. } => Some(arguments.src),
         _ => None,
     }
 }
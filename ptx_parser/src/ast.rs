@@ -4,13 +4,15 @@ use super::{
 };
 use crate::{PtxError, PtxParserState};
 use bitflags::bitflags;
-use std::{cmp::Ordering, num::NonZeroU8};
+use std::{cmp::Ordering, fmt, num::NonZeroU8};
 
 pub enum Statement<P: Operand> {
     Label(P::Ident),
     Variable(MultiVariable<P::Ident>),
     Instruction(Option<PredAt<P::Ident>>, Instruction<P>),
     Block(Vec<Statement<P>>),
+    // Arguments of `.pragma`, without the quotes
+    Pragma(Vec<String>),
 }
 
 // We define the instruction enum through the macro instead of normally, because we have some of how
@@ -160,6 +162,7 @@ ptx_parser_macros::generate_instruction_type!(
             }
         },
         Bra {
+            data: BraData,
             type: !,
             arguments<T::Ident>: {
                 src: T
@@ -188,6 +191,23 @@ ptx_parser_macros::generate_instruction_type!(
                 },
             }
         },
+        CvtPack {
+            data: CvtPackDetails,
+            arguments<T>: {
+                dst: {
+                    repr: T,
+                    type: { Type::Scalar(data.to) },
+                },
+                src1: {
+                    repr: T,
+                    type: { Type::Scalar(ScalarType::F32) },
+                },
+                src2: {
+                    repr: T,
+                    type: { Type::Scalar(ScalarType::F32) },
+                },
+            }
+        },
         Shr {
             data: ShrData,
             type: { Type::Scalar(data.type_.clone()) },
@@ -326,6 +346,14 @@ ptx_parser_macros::generate_instruction_type!(
                 src2: Option<T>,
             }
         },
+        BarArrive {
+            type: Type::Scalar(ScalarType::U32),
+            data: BarData,
+            arguments<T>: {
+                src1: T,
+                src2: T,
+            }
+        },
         Atom {
             type: &data.type_,
             data: AtomDetails,
@@ -508,7 +536,78 @@ ptx_parser_macros::generate_instruction_type!(
         Membar {
             data: MemScope
         },
-        Trap { }
+        Trap { },
+        Video {
+            data: VideoDetails,
+            arguments<T>: {
+                dst: {
+                    repr: T,
+                    type: Type::Scalar(data.dtype)
+                },
+                src1: {
+                    repr: T,
+                    type: Type::Scalar(data.atype)
+                },
+                src2: {
+                    repr: T,
+                    type: Type::Scalar(data.btype)
+                },
+                src3: {
+                    repr: Option<T>,
+                    type: Type::Scalar(data.dtype)
+                },
+            }
+        },
+        ShflSync {
+            type: Type::Scalar(ScalarType::B32),
+            data: ShflSyncDetails,
+            arguments<T>: {
+                dst: T,
+                dst_pred: {
+                    repr: Option<T>,
+                    type: Type::from(ScalarType::Pred)
+                },
+                src: T,
+                src_lane: T,
+                src_opts: T,
+                src_membermask: T
+            }
+        },
+        // `data` is the immediate lookup table of the logic function
+        Lop3 {
+            type: Type::Scalar(ScalarType::B32),
+            data: u8,
+            arguments<T>: {
+                dst: T,
+                src1: T,
+                src2: T,
+                src3: T
+            }
+        },
+        Shf {
+            type: Type::Scalar(ScalarType::B32),
+            data: ShfDetails,
+            arguments<T>: {
+                dst: T,
+                src1: T,
+                src2: T,
+                src3: {
+                    repr: T,
+                    type: Type::Scalar(ScalarType::U32)
+                },
+            }
+        },
+        // d = c + |a - b|
+        Sad {
+            type: Type::Scalar(data.to_owned()),
+            data: ScalarType,
+            arguments<T>: {
+                dst: T,
+                src1: T,
+                src2: T,
+                src3: T
+            }
+        }
     }
 );
 
@@ -578,6 +677,214 @@ pub trait VisitorMut<T: Operand, Err> {
     ) -> Result<(), Err>;
 }
 
+impl<T: Operand> Instruction<T> {
+    // Ends a basic block. `exit` is not parsed yet and `ret` covers it
+    pub fn is_terminator(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Ret { .. } | Instruction::Trap {} | Instruction::Bra { .. }
+        )
+    }
+
+    // Reads or writes memory through an address operand. `red` is not parsed
+    // yet, reductions come in as `atom`
+    pub fn is_memory(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Ld { .. }
+                | Instruction::St { .. }
+                | Instruction::Atom { .. }
+                | Instruction::AtomCas { .. }
+        )
+    }
+
+    // `fence` is parsed as `membar`
+    pub fn is_barrier(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Bar { .. } | Instruction::BarArrive { .. } | Instruction::Membar { .. }
+        )
+    }
+
+    // Oldest `sm_XX` target supporting this form of the instruction. Forms
+    // available everywhere report 10
+    pub fn min_arch(&self) -> u32 {
+        fn scope_arch(scope: MemScope) -> u32 {
+            match scope {
+                MemScope::Cluster => 90,
+                _ => 70,
+            }
+        }
+        fn space_arch(space: StateSpace) -> u32 {
+            match space {
+                StateSpace::SharedCluster => 90,
+                _ => 10,
+            }
+        }
+        match self {
+            Instruction::Ld { data, .. } => {
+                let qualifier = match data.qualifier {
+                    LdStQualifier::Relaxed(scope) | LdStQualifier::Acquire(scope) => {
+                        scope_arch(scope)
+                    }
+                    _ => 10,
+                };
+                let type_ = if data.typ == Type::Scalar(ScalarType::B128) { 70 } else { 10 };
+                qualifier.max(type_).max(space_arch(data.state_space))
+            }
+            Instruction::St { data, .. } => {
+                let qualifier = match data.qualifier {
+                    LdStQualifier::Relaxed(scope) | LdStQualifier::Release(scope) => {
+                        scope_arch(scope)
+                    }
+                    _ => 10,
+                };
+                let type_ = if data.typ == Type::Scalar(ScalarType::B128) { 70 } else { 10 };
+                qualifier.max(type_).max(space_arch(data.state_space))
+            }
+            Instruction::Atom { data, .. } => {
+                let type_ = match data.type_ {
+                    Type::Vector(..) => 90,
+                    Type::Scalar(ScalarType::B128 | ScalarType::BF16 | ScalarType::BF16x2) => 90,
+                    Type::Scalar(ScalarType::F16) => 70,
+                    Type::Scalar(ScalarType::F16x2 | ScalarType::F64) => 60,
+                    _ => 20,
+                };
+                type_.max(space_arch(data.space))
+            }
+            Instruction::AtomCas { data, .. } => {
+                let type_ = match data.type_ {
+                    ScalarType::B128 => 90,
+                    ScalarType::B16 => 70,
+                    _ => 20,
+                };
+                type_.max(space_arch(data.space))
+            }
+            Instruction::Cvt { data, .. } => match data.mode {
+                CvtMode::Tf32 { .. } | CvtMode::FPTruncate { relu: true, .. } => 80,
+                _ if data.to == ScalarType::BF16 || data.from == ScalarType::BF16 => 80,
+                _ => 10,
+            },
+            Instruction::CvtPack { .. } => 80,
+            Instruction::Ex2 { data, .. } => match data.type_ {
+                ScalarType::BF16 | ScalarType::BF16x2 => 90,
+                ScalarType::F16 | ScalarType::F16x2 => 75,
+                _ => 10,
+            },
+            Instruction::Membar { data } => match data {
+                MemScope::Cluster => 90,
+                MemScope::Sys => 20,
+                _ => 10,
+            },
+            Instruction::Lop3 { .. } => 50,
+            Instruction::Shf { .. } => 32,
+            Instruction::ShflSync { .. } | Instruction::Activemask { .. } => 30,
+            Instruction::Bar { arguments, .. } if arguments.src2.is_some() => 20,
+            Instruction::BarArrive { .. }
+            | Instruction::Bfe { .. }
+            | Instruction::Bfi { .. }
+            | Instruction::Brev { .. }
+            | Instruction::Clz { .. }
+            | Instruction::Popc { .. }
+            | Instruction::Prmt { .. }
+            | Instruction::PrmtSlow { .. }
+            | Instruction::Video { .. } => 20,
+            _ => 10,
+        }
+    }
+}
+
+impl<Ident: Copy> Instruction<ParsedOperand<Ident>> {
+    // Bare identifiers (branch targets, call arguments) are passed to `fn_`
+    // as `ParsedOperand::Reg` and written back if it is still a `Reg`
+    pub fn for_each_operand_mut(&mut self, fn_: impl FnMut(&mut ParsedOperand<Ident>)) {
+        struct OperandVisitor<Fn>(Fn);
+
+        impl<Ident: Copy, Fn: FnMut(&mut ParsedOperand<Ident>)>
+            VisitorMut<ParsedOperand<Ident>, std::convert::Infallible> for OperandVisitor<Fn>
+        {
+            fn visit(
+                &mut self,
+                args: &mut ParsedOperand<Ident>,
+                _: Option<(&Type, StateSpace)>,
+                _: bool,
+                _: bool,
+            ) -> Result<(), std::convert::Infallible> {
+                (self.0)(args);
+                Ok(())
+            }
+
+            fn visit_ident(
+                &mut self,
+                args: &mut Ident,
+                _: Option<(&Type, StateSpace)>,
+                _: bool,
+                _: bool,
+            ) -> Result<(), std::convert::Infallible> {
+                let mut operand = ParsedOperand::Reg(*args);
+                (self.0)(&mut operand);
+                if let ParsedOperand::Reg(ident) = operand {
+                    *args = ident;
+                }
+                Ok(())
+            }
+        }
+
+        let _ = visit_mut(self, &mut OperandVisitor(fn_));
+    }
+
+    // Every operand, together with whether it's written. Bare identifiers
+    // (branch targets, call arguments) come as `ParsedOperand::Reg`. Guards
+    // belong to the statement, not to the instruction, and are not included
+    pub fn operands(&self) -> Vec<(ParsedOperand<Ident>, bool)> {
+        let mut operands = Vec::new();
+        let _ = visit(
+            self,
+            &mut |operand: &ParsedOperand<Ident>,
+                  _: Option<(&Type, StateSpace)>,
+                  is_dst: bool,
+                  _: bool| {
+                operands.push((operand.clone(), is_dst));
+                Ok::<_, std::convert::Infallible>(())
+            },
+        );
+        operands
+    }
+
+    // Identifiers whose value is used. Registers holding an address are read,
+    // even when the memory they point to is written
+    pub fn reads(&self) -> Vec<Ident> {
+        let mut reads = Vec::new();
+        for (operand, is_dst) in self.operands() {
+            match operand {
+                ParsedOperand::Reg(name) | ParsedOperand::VecMember(name, _) if !is_dst => {
+                    reads.push(name)
+                }
+                ParsedOperand::RegOffset(name, _)
+                | ParsedOperand::SpecialRegisterDimension(name, _)
+                | ParsedOperand::Generic(name) => reads.push(name),
+                ParsedOperand::VecPack(names) if !is_dst => reads.extend(names),
+                _ => {}
+            }
+        }
+        reads
+    }
+
+    pub fn writes(&self) -> Vec<Ident> {
+        let mut writes = Vec::new();
+        for (operand, is_dst) in self.operands() {
+            match operand {
+                ParsedOperand::Reg(name) | ParsedOperand::VecMember(name, _) if is_dst => {
+                    writes.push(name)
+                }
+                ParsedOperand::VecPack(names) if is_dst => writes.extend(names),
+                _ => {}
+            }
+        }
+        writes
+    }
+}
+
 pub trait VisitorMap<From: Operand, To: Operand, Err> {
     fn visit(
         &mut self,
@@ -614,16 +921,32 @@ where
                 (self)(ident, type_space, is_dst, relaxed_type_check)?,
                 imm,
             ),
+            ParsedOperand::SymbolOffset(ident, imm) => ParsedOperand::SymbolOffset(
+                (self)(ident, type_space, is_dst, relaxed_type_check)?,
+                imm,
+            ),
             ParsedOperand::Imm(imm) => ParsedOperand::Imm(imm),
             ParsedOperand::VecMember(ident, index) => ParsedOperand::VecMember(
                 (self)(ident, type_space, is_dst, relaxed_type_check)?,
                 index,
             ),
+            ParsedOperand::SpecialRegisterDimension(ident, index) => {
+                ParsedOperand::SpecialRegisterDimension(
+                    (self)(ident, type_space, is_dst, relaxed_type_check)?,
+                    index,
+                )
+            }
             ParsedOperand::VecPack(vec) => ParsedOperand::VecPack(
                 vec.into_iter()
                     .map(|ident| (self)(ident, type_space, is_dst, relaxed_type_check))
                     .collect::<Result<Vec<_>, _>>()?,
             ),
+            ParsedOperand::Generic(ident) => ParsedOperand::Generic((self)(
+                ident,
+                type_space,
+                is_dst,
+                relaxed_type_check,
+            )?),
         })
     }
 
@@ -756,6 +1079,13 @@ pub struct MultiVariable<ID> {
     pub count: Option<u32>,
 }
 
+impl<ID> MultiVariable<ID> {
+    // Total storage of the declaration, `.reg .b32 %r<4>;` declares 4 registers
+    pub fn byte_size(&self) -> u64 {
+        self.var.v_type.size_of_bytes() * self.count.unwrap_or(1) as u64
+    }
+}
+
 #[derive(Clone)]
 pub struct Variable<ID> {
     pub align: Option<u32>,
@@ -770,6 +1100,13 @@ pub struct PredAt<ID> {
     pub label: ID,
 }
 
+impl<ID: fmt::Display> fmt::Display for PredAt<ID> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let not = if self.not { "!" } else { "" };
+        write!(f, "@{}{}", not, self.label)
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Hash)]
 pub enum Type {
     // .param.b32 foo;
@@ -806,6 +1143,22 @@ impl Type {
             None => Self::maybe_vector_parsed(prefix, scalar),
         }
     }
+
+    // Pointers are always 64 bit, modules with `.address_size 32` are rejected
+    // by the parser with `PtxError::Unsupported32Bit`
+    pub fn size_of_bytes(&self) -> u64 {
+        match self {
+            Type::Scalar(scalar) => scalar.size_of() as u64,
+            Type::Vector(len, scalar) => *len as u64 * scalar.size_of() as u64,
+            Type::Array(vector, scalar, dimensions) => {
+                let element = vector.map_or(1, |len| len.get() as u64) * scalar.size_of() as u64;
+                dimensions
+                    .iter()
+                    .fold(element, |size, dimension| size * *dimension as u64)
+            }
+            Type::Pointer(..) => 8,
+        }
+    }
 }
 
 impl ScalarType {
@@ -896,13 +1249,19 @@ impl MovDetails {
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ParsedOperand<Ident> {
     Reg(Ident),
     RegOffset(Ident, i32),
+    // Address of a variable or function plus a byte offset: myArr+16
+    SymbolOffset(Ident, i32),
     Imm(ImmediateValue),
     VecMember(Ident, u8),
+    // Dimension of a special register: %tid.x
+    SpecialRegisterDimension(Ident, u8),
     VecPack(Vec<Ident>),
+    // generic(foo)
+    Generic(Ident),
 }
 
 impl<Ident: Copy> Operand for ParsedOperand<Ident> {
@@ -919,7 +1278,7 @@ pub trait Operand: Sized {
     fn from_ident(ident: Self::Ident) -> Self;
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum ImmediateValue {
     U64(u64),
     S64(i64),
@@ -927,6 +1286,52 @@ pub enum ImmediateValue {
     F64(f64),
 }
 
+// Same syntax as accepted by the parser, floats are printed as their exact
+// hex representation
+impl fmt::Display for ImmediateValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImmediateValue::U64(x) => write!(f, "{}U", x),
+            ImmediateValue::S64(x) => write!(f, "{}", x),
+            ImmediateValue::F32(x) => write!(f, "0f{:08X}", x.to_bits()),
+            ImmediateValue::F64(x) => write!(f, "0d{:016X}", x.to_bits()),
+        }
+    }
+}
+
+impl<Ident: fmt::Display> fmt::Display for ParsedOperand<Ident> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsedOperand::Reg(name) => write!(f, "{}", name),
+            ParsedOperand::RegOffset(name, offset) | ParsedOperand::SymbolOffset(name, offset) => {
+                write!(f, "{}+{}", name, offset)
+            }
+            ParsedOperand::Imm(value) => write!(f, "{}", value),
+            ParsedOperand::VecMember(name, index)
+            | ParsedOperand::SpecialRegisterDimension(name, index) => {
+                let member = match index {
+                    0 => "x",
+                    1 => "y",
+                    2 => "z",
+                    _ => "w",
+                };
+                write!(f, "{}.{}", name, member)
+            }
+            ParsedOperand::VecPack(names) => {
+                write!(f, "{{")?;
+                for (index, name) in names.iter().enumerate() {
+                    if index != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", name)?;
+                }
+                write!(f, "}}")
+            }
+            ParsedOperand::Generic(name) => write!(f, "generic({})", name),
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum StCacheOperator {
     Writeback,
@@ -992,6 +1397,8 @@ pub enum RoundingMode {
 
 pub struct LdDetails {
     pub qualifier: LdStQualifier,
+    // `.weak` is the default qualifier, this tells if it was spelled out
+    pub weak_explicit: bool,
     pub state_space: StateSpace,
     pub caching: LdCacheOperator,
     pub typ: Type,
@@ -1000,6 +1407,8 @@ pub struct LdDetails {
 
 pub struct StData {
     pub qualifier: LdStQualifier,
+    // `.weak` is the default qualifier, this tells if it was spelled out
+    pub weak_explicit: bool,
     pub state_space: StateSpace,
     pub caching: StCacheOperator,
     pub typ: Type,
@@ -1010,6 +1419,12 @@ pub struct RetData {
     pub uniform: bool,
 }
 
+#[derive(Copy, Clone)]
+pub struct BraData {
+    // `bra.uni`, all active threads of the warp take the same path
+    pub uniform: bool,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum TuningDirective {
     MaxNReg(u32),
@@ -1055,19 +1470,161 @@ pub struct Function<'a, ID, S> {
     pub body: Option<Vec<S>>,
 }
 
+impl<'a, ID, T: Operand> Function<'a, ID, Statement<T>> {
+    pub fn is_kernel(&self) -> bool {
+        matches!(self.func_directive.name, MethodName::Kernel(_))
+    }
+
+    // Statements in nested blocks are counted, the blocks themselves are not
+    pub fn statement_count(&self) -> usize {
+        self.statements().count()
+    }
+
+    pub fn instruction_count(&self) -> usize {
+        self.statements()
+            .filter(|statement| matches!(statement, Statement::Instruction(..)))
+            .count()
+    }
+
+    // A basic block starts at the first instruction of the function and at
+    // the first instruction following a label or a terminator
+    pub fn basic_block_count(&self) -> usize {
+        let mut count = 0;
+        let mut in_block = false;
+        for statement in self.statements() {
+            match statement {
+                Statement::Label(_) => in_block = false,
+                Statement::Instruction(_, instruction) => {
+                    if !in_block {
+                        count += 1;
+                    }
+                    in_block = !instruction.is_terminator();
+                }
+                _ => {}
+            }
+        }
+        count
+    }
+
+    fn statements(&self) -> impl Iterator<Item = &Statement<T>> {
+        fn flatten<T: Operand>(
+            body: &[Statement<T>],
+        ) -> Box<dyn Iterator<Item = &Statement<T>> + '_> {
+            Box::new(body.iter().flat_map(|statement| match statement {
+                Statement::Block(block) => flatten(block),
+                statement => Box::new(std::iter::once(statement)),
+            }))
+        }
+        flatten(self.body.as_deref().unwrap_or(&[]))
+    }
+
+    // Editable view of the body, `None` for declarations
+    pub fn statement_list(&mut self) -> Option<StatementList<'_, T>> {
+        self.body.as_mut().map(StatementList::new)
+    }
+}
+
+// Statements of a function body or of a nested block, edited by index. A
+// nested block is a single statement: inserting after it inserts after the
+// closing brace and removing it removes everything inside. Its own statements
+// are edited through `block`. Indices past the end panic, like `Vec` does
+pub struct StatementList<'a, T: Operand> {
+    statements: &'a mut Vec<Statement<T>>,
+}
+
+impl<'a, T: Operand> StatementList<'a, T> {
+    pub fn new(statements: &'a mut Vec<Statement<T>>) -> Self {
+        StatementList { statements }
+    }
+
+    pub fn len(&self) -> usize {
+        self.statements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Statement<T>> {
+        self.statements.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Statement<T>> {
+        self.statements.iter()
+    }
+
+    pub fn block(&mut self, index: usize) -> Option<StatementList<'_, T>> {
+        match self.statements.get_mut(index) {
+            Some(Statement::Block(block)) => Some(StatementList::new(block)),
+            _ => None,
+        }
+    }
+
+    // Inserts before the statement at `index`, `len()` appends
+    pub fn insert(&mut self, index: usize, statements: impl IntoIterator<Item = Statement<T>>) {
+        self.statements.splice(index..index, statements);
+    }
+
+    pub fn insert_after(
+        &mut self,
+        index: usize,
+        statements: impl IntoIterator<Item = Statement<T>>,
+    ) {
+        assert!(index < self.statements.len());
+        self.insert(index + 1, statements);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Statement<T> {
+        self.statements.remove(index)
+    }
+
+    // Replaces one statement with any number of statements, returns the old one
+    pub fn replace(
+        &mut self,
+        index: usize,
+        statements: impl IntoIterator<Item = Statement<T>>,
+    ) -> Statement<T> {
+        self.splice(index..index + 1, statements).pop().unwrap()
+    }
+
+    pub fn splice(
+        &mut self,
+        range: std::ops::Range<usize>,
+        statements: impl IntoIterator<Item = Statement<T>>,
+    ) -> Vec<Statement<T>> {
+        self.statements.splice(range, statements).collect()
+    }
+}
+
 pub enum Directive<'input, O: Operand> {
     Variable(LinkingDirective, Variable<O::Ident>),
     Method(
         LinkingDirective,
         Function<'input, &'input str, Statement<O>>,
     ),
+    DwarfLine(&'input str),
 }
 
 pub struct Module<'input> {
     pub version: (u8, u8),
+    // `.target sm_90a` is (90, Some('a'))
+    pub target: (u32, Option<char>),
     pub directives: Vec<Directive<'input, ParsedOperand<&'input str>>>,
 }
 
+impl<'input> Module<'input> {
+    // Kernels, both declared and defined
+    pub fn entry_points(
+        &self,
+    ) -> impl Iterator<Item = &Function<'input, &'input str, Statement<ParsedOperand<&'input str>>>>
+    {
+        self.directives.iter().filter_map(|directive| match directive {
+            Directive::Method(_, func) if func.is_kernel() => Some(func),
+            _ => None,
+        })
+    }
+}
+
 #[derive(Copy, Clone)]
 pub enum MulDetails {
     Integer {
@@ -1201,6 +1758,18 @@ impl TryFrom<(RawSetpCompareOp, ScalarKind)> for SetpCompareInt {
         match (value, kind) {
             (RawSetpCompareOp::Eq, _) => Ok(SetpCompareInt::Eq),
             (RawSetpCompareOp::Ne, _) => Ok(SetpCompareInt::NotEq),
+            // Bit types have no ordering, only equality is defined
+            (
+                RawSetpCompareOp::Lt
+                | RawSetpCompareOp::Lo
+                | RawSetpCompareOp::Le
+                | RawSetpCompareOp::Ls
+                | RawSetpCompareOp::Gt
+                | RawSetpCompareOp::Hi
+                | RawSetpCompareOp::Ge
+                | RawSetpCompareOp::Hs,
+                ScalarKind::Bit,
+            ) => Err(PtxError::SyntaxError),
             (RawSetpCompareOp::Lt | RawSetpCompareOp::Lo, ScalarKind::Signed) => {
                 Ok(SetpCompareInt::SignedLess)
             }
@@ -1354,6 +1923,9 @@ pub struct CvtDetails {
     pub from: ScalarType,
     pub to: ScalarType,
     pub mode: CvtMode,
+    // For integer destinations, clamp to the destination range instead of
+    // wrapping around
+    pub saturate: bool,
 }
 
 pub enum CvtMode {
@@ -1372,6 +1944,7 @@ pub enum CvtMode {
         // float rounding
         rounding: RoundingMode,
         flush_to_zero: Option<bool>,
+        relu: bool,
     },
     FPRound {
         integer_rounding: Option<RoundingMode>,
@@ -1389,6 +1962,29 @@ pub enum CvtMode {
     // float from int, ftz is allowed in the grammar, but clearly nonsensical
     FPFromSigned(RoundingMode),   // float rounding
     FPFromUnsigned(RoundingMode), // float rounding
+    // tf32 from f32, the result is stored in a .b32 register
+    Tf32 {
+        rounding: Tf32RoundingMode,
+        relu: bool,
+        saturate_finite: bool,
+    },
+}
+
+// Two f32 values converted and packed into a single f16x2/bf16x2 value, `a`
+// goes into the upper half
+pub struct CvtPackDetails {
+    pub to: ScalarType,
+    pub rounding: RoundingMode,
+    pub flush_to_zero: bool,
+    pub relu: bool,
+    pub saturate_finite: bool,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum Tf32RoundingMode {
+    NearestAway,
+    NearestEven,
+    Zero,
 }
 
 impl CvtDetails {
@@ -1397,6 +1993,7 @@ impl CvtDetails {
         rnd: Option<RawRoundingMode>,
         ftz: bool,
         saturate: bool,
+        relu: bool,
         dst: ScalarType,
         src: ScalarType,
     ) -> Self {
@@ -1426,6 +2023,7 @@ impl CvtDetails {
                 Ordering::Less => CvtMode::FPTruncate {
                     rounding: unwrap_rounding(),
                     flush_to_zero,
+                    relu,
                 },
                 Ordering::Equal => CvtMode::FPRound {
                     integer_rounding: rounding,
@@ -1478,10 +2076,24 @@ impl CvtDetails {
                 CvtMode::Bitcast
             }
         };
+        // .relu is only defined for narrowing float conversions
+        if relu && !matches!(mode, CvtMode::FPTruncate { .. }) {
+            errors.push(PtxError::SyntaxError);
+        }
+        // Widening within the same signedness always fits, there's nothing to clamp
+        if saturate
+            && matches!(
+                mode,
+                CvtMode::ZeroExtend | CvtMode::SignExtend | CvtMode::Bitcast
+            )
+        {
+            errors.push(PtxError::SyntaxError);
+        }
         CvtDetails {
             mode,
             to: dst,
             from: src,
+            saturate,
         }
     }
 }
@@ -1510,6 +2122,51 @@ pub enum RightShiftKind {
     Logical,
 }
 
+pub struct VideoDetails {
+    pub op: VideoOp,
+    pub dtype: ScalarType,
+    pub atype: ScalarType,
+    pub btype: ScalarType,
+    pub saturate: bool,
+}
+
+pub enum VideoOp {
+    Shl(VideoShiftMode),
+    Shr(VideoShiftMode),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum VideoShiftMode {
+    Clamp,
+    Wrap,
+}
+
+pub struct ShflSyncDetails {
+    pub mode: ShuffleMode,
+}
+
+// Funnel shift of the 64-bit value `b:a` by `c` bits, clamped to 32 or
+// wrapped modulo 32 like the video shifts
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ShfDetails {
+    pub direction: ShiftDirection,
+    pub mode: VideoShiftMode,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ShiftDirection {
+    Left,
+    Right,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ShuffleMode {
+    Up,
+    Down,
+    BFly,
+    Idx,
+}
+
 pub struct CvtaDetails {
     pub state_space: StateSpace,
     pub direction: CvtaDirection,
@@ -1532,6 +2189,9 @@ pub enum MadDetails {
         control: MulIntControl,
         saturate: bool,
         type_: ScalarType,
+        // mad.cc and madc
+        carry_in: bool,
+        carry_out: bool,
     },
     Float(ArithFloat),
 }
@@ -1601,6 +2261,15 @@ pub enum RcpKind {
 
 pub struct BarData {
     pub aligned: bool,
+    // Only kept so the instruction can be printed back the way it was written,
+    // `bar.sync` is the same as `barrier.sync.aligned`
+    pub spelling: BarSpelling,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BarSpelling {
+    Bar,
+    Barrier,
 }
 
 pub struct AtomDetails {
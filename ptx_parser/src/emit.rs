@@ -0,0 +1,1104 @@
+use crate::ast::*;
+use crate::{AtomSemantics, MemScope, ScalarType, SetpBoolPostOp, StateSpace};
+use std::fmt::{self, Write};
+
+// Lays out a function body for re-emission. Labels go on their own line
+// without indentation, nested blocks are wrapped in braces and indented, and
+// a blank line separates a terminator from the statements that follow it.
+// `write_statement` writes the text of each variable, pragma and instruction
+// (without guard and semicolon), see `write_instruction` for the latter
+pub fn write_body<W: Write, T: Operand>(
+    w: &mut W,
+    body: &[Statement<T>],
+    mut write_statement: impl FnMut(&mut W, &Statement<T>) -> fmt::Result,
+) -> fmt::Result
+where
+    T::Ident: fmt::Display,
+{
+    writeln!(w, "{{")?;
+    write_body_impl(w, body, 1, &mut write_statement)?;
+    writeln!(w, "}}")
+}
+
+const INDENT: &str = "    ";
+
+fn write_body_impl<W: Write, T: Operand>(
+    w: &mut W,
+    body: &[Statement<T>],
+    depth: usize,
+    write_statement: &mut impl FnMut(&mut W, &Statement<T>) -> fmt::Result,
+) -> fmt::Result
+where
+    T::Ident: fmt::Display,
+{
+    for (index, statement) in body.iter().enumerate() {
+        match statement {
+            Statement::Label(label) => {
+                writeln!(w, "{label}:")?;
+                continue;
+            }
+            Statement::Block(block) => {
+                writeln!(w, "{}{{", INDENT.repeat(depth))?;
+                write_body_impl(w, block, depth + 1, write_statement)?;
+                writeln!(w, "{}}}", INDENT.repeat(depth))?;
+                continue;
+            }
+            Statement::Variable(_) | Statement::Pragma(_) => {
+                w.write_str(&INDENT.repeat(depth))?;
+            }
+            Statement::Instruction(guard, _) => {
+                w.write_str(&INDENT.repeat(depth))?;
+                if let Some(guard) = guard {
+                    write!(w, "{guard} ")?;
+                }
+            }
+        }
+        write_statement(w, statement)?;
+        writeln!(w, ";")?;
+        let terminator = matches!(statement, Statement::Instruction(_, instruction) if instruction.is_terminator());
+        if terminator && index + 1 < body.len() {
+            writeln!(w)?;
+        }
+    }
+    Ok(())
+}
+
+// Module text with modifiers in a fixed order, defaults left out and one
+// statement per line. Modules that only differ in spelling (comments,
+// whitespace, `bar.sync` vs `barrier.sync.aligned`, `setp.lo` vs `setp.lt`)
+// give the same string, so it can be used for golden tests
+pub fn to_canonical_string(module: &Module) -> String {
+    let mut result = String::new();
+    // Writing to a String can't fail
+    write_module(&mut result, module).unwrap();
+    result
+}
+
+fn write_module<W: Write>(w: &mut W, module: &Module) -> fmt::Result {
+    let (major, minor) = module.version;
+    writeln!(w, ".version {major}.{minor}")?;
+    let (sm, variant) = module.target;
+    write!(w, ".target sm_{sm}")?;
+    if let Some(variant) = variant {
+        write!(w, "{variant}")?;
+    }
+    writeln!(w)?;
+    writeln!(w, ".address_size 64")?;
+    for directive in module.directives.iter() {
+        writeln!(w)?;
+        match directive {
+            Directive::Variable(linking, var) => {
+                write_linking(w, *linking)?;
+                write_variable(w, var, None)?;
+                writeln!(w, ";")?;
+            }
+            Directive::Method(linking, func) => write_function(w, *linking, func)?,
+            Directive::DwarfLine(line) => writeln!(w, "@@DWARF {line}")?,
+        }
+    }
+    Ok(())
+}
+
+fn write_linking<W: Write>(w: &mut W, linking: LinkingDirective) -> fmt::Result {
+    for (flag, text) in [
+        (LinkingDirective::EXTERN, ".extern "),
+        (LinkingDirective::VISIBLE, ".visible "),
+        (LinkingDirective::WEAK, ".weak "),
+    ] {
+        if linking.contains(flag) {
+            w.write_str(text)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_function<W: Write, T: Operand + fmt::Display>(
+    w: &mut W,
+    linking: LinkingDirective,
+    func: &Function<&str, Statement<T>>,
+) -> fmt::Result
+where
+    T::Ident: fmt::Display,
+{
+    write_linking(w, linking)?;
+    let declaration = &func.func_directive;
+    match declaration.name {
+        MethodName::Kernel(name) => write!(w, ".entry {name}")?,
+        MethodName::Func(name) => {
+            w.write_str(".func ")?;
+            if !declaration.return_arguments.is_empty() {
+                write_parameters(w, &declaration.return_arguments)?;
+                w.write_str(" ")?;
+            }
+            w.write_str(name)?;
+        }
+    }
+    write_parameters(w, &declaration.input_arguments)?;
+    for tuning in func.tuning.iter() {
+        match tuning {
+            TuningDirective::MaxNReg(count) => write!(w, "\n.maxnreg {count}")?,
+            TuningDirective::MaxNtid(x, y, z) => write!(w, "\n.maxntid {x}, {y}, {z}")?,
+            TuningDirective::ReqNtid(x, y, z) => write!(w, "\n.reqntid {x}, {y}, {z}")?,
+            TuningDirective::MinNCtaPerSm(count) => write!(w, "\n.minnctapersm {count}")?,
+        }
+    }
+    match func.body {
+        Some(ref body) => {
+            writeln!(w)?;
+            write_body(w, body, |w, statement| match statement {
+                Statement::Variable(var) => write_variable(w, &var.var, var.count),
+                Statement::Pragma(args) => {
+                    w.write_str(".pragma ")?;
+                    for (index, arg) in args.iter().enumerate() {
+                        let separator = if index == 0 { "" } else { ", " };
+                        write!(w, "{separator}\"{arg}\"")?;
+                    }
+                    Ok(())
+                }
+                Statement::Instruction(_, instruction) => write_instruction(w, instruction),
+                Statement::Label(_) | Statement::Block(_) => Ok(()),
+            })
+        }
+        None => writeln!(w, ";"),
+    }
+}
+
+fn write_parameters<W: Write, ID: fmt::Display>(
+    w: &mut W,
+    parameters: &[Variable<ID>],
+) -> fmt::Result {
+    w.write_str("(")?;
+    for (index, parameter) in parameters.iter().enumerate() {
+        if index != 0 {
+            w.write_str(", ")?;
+        }
+        write_variable(w, parameter, None)?;
+    }
+    w.write_str(")")
+}
+
+fn write_variable<W: Write, ID: fmt::Display>(
+    w: &mut W,
+    var: &Variable<ID>,
+    count: Option<u32>,
+) -> fmt::Result {
+    w.write_str(state_space(var.state_space))?;
+    if let Some(align) = var.align {
+        write!(w, " .align {align}")?;
+    }
+    let (scalar, dimensions) = match var.v_type {
+        Type::Array(vector, scalar, ref dimensions) => {
+            if let Some(len) = vector {
+                write!(w, " .v{len}")?;
+            }
+            (scalar, &dimensions[..])
+        }
+        ref type_ => (scalar_of(type_), &[][..]),
+    };
+    if let Type::Vector(len, _) = var.v_type {
+        write!(w, " .v{len}")?;
+    }
+    write!(w, " {} {}", scalar_type(scalar), var.name)?;
+    if let Some(count) = count {
+        write!(w, "<{count}>")?;
+    }
+    for dimension in dimensions {
+        match dimension {
+            0 => w.write_str("[]")?,
+            _ => write!(w, "[{dimension}]")?,
+        }
+    }
+    if !var.array_init.is_empty() {
+        // Initializers are stored as bytes, they are written back as one
+        // value per element
+        let values = var
+            .array_init
+            .chunks(scalar.size_of() as usize)
+            .map(|bytes| initializer_value(scalar, bytes))
+            .collect::<Vec<_>>();
+        if dimensions.is_empty() {
+            write!(w, " = {}", values[0])?;
+        } else {
+            write!(w, " = {{")?;
+            for (index, value) in values.iter().enumerate() {
+                let separator = if index == 0 { "" } else { ", " };
+                write!(w, "{separator}{value}")?;
+            }
+            write!(w, "}}")?;
+        }
+    }
+    Ok(())
+}
+
+fn initializer_value(type_: ScalarType, bytes: &[u8]) -> ImmediateValue {
+    let mut buffer = [0u8; 8];
+    buffer[..bytes.len()].copy_from_slice(bytes);
+    let value = u64::from_le_bytes(buffer);
+    let bits = bytes.len() as u32 * 8;
+    match type_.kind() {
+        // Half precision values are exactly representable as .f32 literals
+        ScalarKind::Float if type_ == ScalarType::BF16 => {
+            ImmediateValue::F32(f32::from_bits((value as u32) << 16))
+        }
+        ScalarKind::Float if bits == 16 => ImmediateValue::F32(f16_to_f32(value as u16)),
+        ScalarKind::Float if bits == 32 => ImmediateValue::F32(f32::from_bits(value as u32)),
+        ScalarKind::Float => ImmediateValue::F64(f64::from_bits(value)),
+        // Sign extend from the element width
+        ScalarKind::Signed => ImmediateValue::S64((value << (64 - bits)) as i64 >> (64 - bits)),
+        _ => ImmediateValue::U64(value),
+    }
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    let magnitude = match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    };
+    sign * magnitude
+}
+
+// Writes the instruction without guard and terminating semicolon
+pub fn write_instruction<W: Write, T: Operand + fmt::Display>(
+    w: &mut W,
+    instruction: &Instruction<T>,
+) -> fmt::Result
+where
+    T::Ident: fmt::Display,
+{
+    match instruction {
+        Instruction::Mov { data, arguments } => {
+            write!(w, "mov{}", type_name(&data.typ))?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Ld { data, arguments } => {
+            w.write_str("ld")?;
+            let caching = match data.caching {
+                LdCacheOperator::Cached => "",
+                LdCacheOperator::L2Only => ".cg",
+                LdCacheOperator::Streaming => ".cs",
+                LdCacheOperator::LastUse => ".lu",
+                LdCacheOperator::Uncached => ".cv",
+            };
+            if data.non_coherent {
+                write!(w, "{}{caching}.nc", state_space(data.state_space))?;
+            } else {
+                write_qualifier(w, data.qualifier)?;
+                write!(w, "{}{caching}", state_space(data.state_space))?;
+            }
+            write!(
+                w,
+                "{} {}, [{}]",
+                type_name(&data.typ),
+                arguments.dst,
+                arguments.src
+            )
+        }
+        Instruction::St { data, arguments } => {
+            w.write_str("st")?;
+            write_qualifier(w, data.qualifier)?;
+            let caching = match data.caching {
+                StCacheOperator::Writeback => "",
+                StCacheOperator::L2Only => ".cg",
+                StCacheOperator::Streaming => ".cs",
+                StCacheOperator::Writethrough => ".wt",
+            };
+            write!(w, "{}{caching}", state_space(data.state_space))?;
+            write!(
+                w,
+                "{} [{}], {}",
+                type_name(&data.typ),
+                arguments.src1,
+                arguments.src2
+            )
+        }
+        Instruction::Add { data, arguments } => {
+            write_arith(w, "add", data)?;
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])
+        }
+        Instruction::Sub { data, arguments } => {
+            write_arith(w, "sub", data)?;
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])
+        }
+        Instruction::Mul { data, arguments } => {
+            match data {
+                MulDetails::Integer { type_, control } => {
+                    write!(w, "mul{}{}", mul_control(*control), scalar_type(*type_))?
+                }
+                MulDetails::Float(float) => write_arith_float(w, "mul", float)?,
+            }
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])
+        }
+        Instruction::Mad { data, arguments } => {
+            match data {
+                MadDetails::Integer {
+                    control,
+                    saturate,
+                    type_,
+                    carry_in,
+                    carry_out,
+                } => {
+                    let opcode = if *carry_in { "madc" } else { "mad" };
+                    let saturate = if *saturate { ".sat" } else { "" };
+                    let carry_out = if *carry_out { ".cc" } else { "" };
+                    write!(
+                        w,
+                        "{opcode}{}{saturate}{carry_out}{}",
+                        mul_control(*control),
+                        scalar_type(*type_)
+                    )?
+                }
+                MadDetails::Float(float) => write_arith_float(w, "mad", float)?,
+            }
+            write_operands(
+                w,
+                &[
+                    &arguments.dst,
+                    &arguments.src1,
+                    &arguments.src2,
+                    &arguments.src3,
+                ],
+            )
+        }
+        Instruction::Fma { data, arguments } => {
+            write_arith_float(w, "fma", data)?;
+            write_operands(
+                w,
+                &[
+                    &arguments.dst,
+                    &arguments.src1,
+                    &arguments.src2,
+                    &arguments.src3,
+                ],
+            )
+        }
+        Instruction::Setp { data, arguments } => {
+            write_setp(w, data, None)?;
+            write_predicate_pair(w, &arguments.dst1, &arguments.dst2)?;
+            write!(w, ", {}, {}", arguments.src1, arguments.src2)
+        }
+        Instruction::SetpBool { data, arguments } => {
+            write_setp(w, &data.base, Some(data.bool_op))?;
+            write_predicate_pair(w, &arguments.dst1, &arguments.dst2)?;
+            let negate = if data.negate_src3 { "!" } else { "" };
+            write!(
+                w,
+                ", {}, {}, {negate}{}",
+                arguments.src1, arguments.src2, arguments.src3
+            )
+        }
+        Instruction::Not { data, arguments } => {
+            write!(w, "not{}", scalar_type(*data))?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Or { data, arguments } => {
+            write!(w, "or{}", scalar_type(*data))?;
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])
+        }
+        Instruction::And { data, arguments } => {
+            write!(w, "and{}", scalar_type(*data))?;
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])
+        }
+        Instruction::Xor { data, arguments } => {
+            write!(w, "xor{}", scalar_type(*data))?;
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])
+        }
+        Instruction::Bra { data, arguments } => {
+            let uniform = if data.uniform { ".uni" } else { "" };
+            write!(w, "bra{uniform} {}", arguments.src)
+        }
+        Instruction::Call { data, arguments } => {
+            let uniform = if data.uniform { ".uni" } else { "" };
+            write!(w, "call{uniform} ")?;
+            if !arguments.return_arguments.is_empty() {
+                w.write_str("(")?;
+                for (index, argument) in arguments.return_arguments.iter().enumerate() {
+                    let separator = if index == 0 { "" } else { ", " };
+                    write!(w, "{separator}{argument}")?;
+                }
+                w.write_str("), ")?;
+            }
+            write!(w, "{}", arguments.func)?;
+            if !arguments.input_arguments.is_empty() {
+                w.write_str(", (")?;
+                for (index, argument) in arguments.input_arguments.iter().enumerate() {
+                    let separator = if index == 0 { "" } else { ", " };
+                    write!(w, "{separator}{argument}")?;
+                }
+                w.write_str(")")?;
+            }
+            Ok(())
+        }
+        Instruction::Cvt { data, arguments } => {
+            write_cvt(w, data)?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::CvtPack { data, arguments } => {
+            let ftz = if data.flush_to_zero { ".ftz" } else { "" };
+            let relu = if data.relu { ".relu" } else { "" };
+            let saturate = if data.saturate_finite {
+                ".satfinite"
+            } else {
+                ""
+            };
+            write!(
+                w,
+                "cvt{}{ftz}{relu}{saturate}{}.f32",
+                rounding(data.rounding),
+                scalar_type(data.to)
+            )?;
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])
+        }
+        Instruction::Shr { data, arguments } => {
+            write!(w, "shr{}", scalar_type(data.type_))?;
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])
+        }
+        Instruction::Shl { data, arguments } => {
+            write!(w, "shl{}", scalar_type(*data))?;
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])
+        }
+        Instruction::Ret { data } => w.write_str(if data.uniform { "ret.uni" } else { "ret" }),
+        Instruction::Cvta { data, arguments } => {
+            let direction = match data.direction {
+                CvtaDirection::GenericToExplicit => ".to",
+                CvtaDirection::ExplicitToGeneric => "",
+            };
+            write!(w, "cvta{direction}{}.u64", state_space(data.state_space))?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Abs { data, arguments } => {
+            write!(
+                w,
+                "abs{}{}",
+                ftz(data.flush_to_zero),
+                scalar_type(data.type_)
+            )?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Neg { data, arguments } => {
+            write!(
+                w,
+                "neg{}{}",
+                ftz(data.flush_to_zero),
+                scalar_type(data.type_)
+            )?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Min { data, arguments } => {
+            write_min_max(w, "min", data)?;
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])
+        }
+        Instruction::Max { data, arguments } => {
+            write_min_max(w, "max", data)?;
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])
+        }
+        Instruction::Rcp { data, arguments } => {
+            write_rcp(w, "rcp", data)?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Sqrt { data, arguments } => {
+            write_rcp(w, "sqrt", data)?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Rsqrt { data, arguments } => {
+            write!(
+                w,
+                "rsqrt.approx{}{}",
+                ftz(data.flush_to_zero),
+                scalar_type(data.type_)
+            )?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Ex2 { data, arguments } => {
+            write!(
+                w,
+                "ex2.approx{}{}",
+                ftz(data.flush_to_zero),
+                scalar_type(data.type_)
+            )?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Sin { data, arguments } => {
+            write!(w, "sin.approx{}.f32", ftz(Some(data.flush_to_zero)))?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Cos { data, arguments } => {
+            write!(w, "cos.approx{}.f32", ftz(Some(data.flush_to_zero)))?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Lg2 { data, arguments } => {
+            write!(w, "lg2.approx{}.f32", ftz(Some(data.flush_to_zero)))?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Selp { data, arguments } => {
+            write!(w, "selp{}", scalar_type(*data))?;
+            write_operands(
+                w,
+                &[
+                    &arguments.dst,
+                    &arguments.src1,
+                    &arguments.src2,
+                    &arguments.src3,
+                ],
+            )
+        }
+        // `bar.sync` is written as its equivalent `barrier.sync.aligned`
+        Instruction::Bar { data, arguments } => {
+            let (opcode, aligned) = bar_opcode(data);
+            write!(w, "{opcode}.sync{aligned} {}", arguments.src1)?;
+            match arguments.src2 {
+                Some(ref src2) => write!(w, ", {src2}"),
+                None => Ok(()),
+            }
+        }
+        Instruction::BarArrive { data, arguments } => {
+            let (opcode, aligned) = bar_opcode(data);
+            write!(w, "{opcode}.arrive{aligned}")?;
+            write_operands(w, &[&arguments.src1, &arguments.src2])
+        }
+        Instruction::Atom { data, arguments } => {
+            write_atom_prefix(w, data.semantics, data.scope, data.space)?;
+            let op = match data.op {
+                AtomicOp::And => ".and",
+                AtomicOp::Or => ".or",
+                AtomicOp::Xor => ".xor",
+                AtomicOp::Exchange => ".exch",
+                AtomicOp::Add | AtomicOp::FloatAdd => ".add",
+                AtomicOp::IncrementWrap => ".inc",
+                AtomicOp::DecrementWrap => ".dec",
+                AtomicOp::SignedMin | AtomicOp::UnsignedMin | AtomicOp::FloatMin => ".min",
+                AtomicOp::SignedMax | AtomicOp::UnsignedMax | AtomicOp::FloatMax => ".max",
+            };
+            // Half precision atomics don't flush subnormals and must say so
+            let noftz = match scalar_of(&data.type_) {
+                ScalarType::F16 | ScalarType::F16x2 | ScalarType::BF16 | ScalarType::BF16x2 => {
+                    ".noftz"
+                }
+                _ => "",
+            };
+            write!(
+                w,
+                "{op}{noftz}{} {}, [{}], {}",
+                type_name(&data.type_),
+                arguments.dst,
+                arguments.src1,
+                arguments.src2
+            )
+        }
+        Instruction::AtomCas { data, arguments } => {
+            write_atom_prefix(w, data.semantics, data.scope, data.space)?;
+            write!(
+                w,
+                ".cas{} {}, [{}], {}, {}",
+                scalar_type(data.type_),
+                arguments.dst,
+                arguments.src1,
+                arguments.src2,
+                arguments.src3
+            )
+        }
+        Instruction::Div { data, arguments } => {
+            match data {
+                DivDetails::Unsigned(type_) | DivDetails::Signed(type_) => {
+                    write!(w, "div{}", scalar_type(*type_))?
+                }
+                DivDetails::Float(float) => {
+                    let kind = match float.kind {
+                        DivFloatKind::Approx => ".approx",
+                        DivFloatKind::ApproxFull => ".full",
+                        DivFloatKind::Rounding(mode) => rounding(mode),
+                    };
+                    write!(
+                        w,
+                        "div{kind}{}{}",
+                        ftz(float.flush_to_zero),
+                        scalar_type(float.type_)
+                    )?
+                }
+            }
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])
+        }
+        Instruction::Clz { data, arguments } => {
+            write!(w, "clz{}", scalar_type(*data))?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Brev { data, arguments } => {
+            write!(w, "brev{}", scalar_type(*data))?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Popc { data, arguments } => {
+            write!(w, "popc{}", scalar_type(*data))?;
+            write_operands(w, &[&arguments.dst, &arguments.src])
+        }
+        Instruction::Rem { data, arguments } => {
+            write!(w, "rem{}", scalar_type(*data))?;
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])
+        }
+        Instruction::Bfe { data, arguments } => {
+            write!(w, "bfe{}", scalar_type(*data))?;
+            write_operands(
+                w,
+                &[
+                    &arguments.dst,
+                    &arguments.src1,
+                    &arguments.src2,
+                    &arguments.src3,
+                ],
+            )
+        }
+        Instruction::Bfi { data, arguments } => {
+            write!(w, "bfi{}", scalar_type(*data))?;
+            write_operands(
+                w,
+                &[
+                    &arguments.dst,
+                    &arguments.src1,
+                    &arguments.src2,
+                    &arguments.src3,
+                    &arguments.src4,
+                ],
+            )
+        }
+        Instruction::PrmtSlow { arguments } => {
+            w.write_str("prmt.b32")?;
+            write_operands(
+                w,
+                &[
+                    &arguments.dst,
+                    &arguments.src1,
+                    &arguments.src2,
+                    &arguments.src3,
+                ],
+            )
+        }
+        Instruction::Prmt { data, arguments } => {
+            w.write_str("prmt.b32")?;
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2, data])
+        }
+        Instruction::Activemask { arguments } => write!(w, "activemask.b32 {}", arguments.dst),
+        Instruction::Membar { data } => match data {
+            MemScope::Gpu => w.write_str("membar.gl"),
+            scope => write!(w, "membar{}", mem_scope(*scope)),
+        },
+        Instruction::Trap {} => w.write_str("trap"),
+        Instruction::Video { data, arguments } => {
+            let (opcode, mode) = match data.op {
+                VideoOp::Shl(mode) => ("vshl", mode),
+                VideoOp::Shr(mode) => ("vshr", mode),
+            };
+            let mode = match mode {
+                VideoShiftMode::Clamp => ".clamp",
+                VideoShiftMode::Wrap => ".wrap",
+            };
+            let saturate = if data.saturate { ".sat" } else { "" };
+            write!(
+                w,
+                "{opcode}{}{}{}{saturate}{mode}",
+                scalar_type(data.dtype),
+                scalar_type(data.atype),
+                scalar_type(data.btype)
+            )?;
+            write_operands(w, &[&arguments.dst, &arguments.src1, &arguments.src2])?;
+            match arguments.src3 {
+                Some(ref src3) => write!(w, ", {src3}"),
+                None => Ok(()),
+            }
+        }
+        Instruction::ShflSync { data, arguments } => {
+            let mode = match data.mode {
+                ShuffleMode::Up => ".up",
+                ShuffleMode::Down => ".down",
+                ShuffleMode::BFly => ".bfly",
+                ShuffleMode::Idx => ".idx",
+            };
+            write!(w, "shfl.sync{mode}.b32")?;
+            write_predicate_pair(w, &arguments.dst, &arguments.dst_pred)?;
+            write!(
+                w,
+                ", {}, {}, {}, {}",
+                arguments.src, arguments.src_lane, arguments.src_opts, arguments.src_membermask
+            )
+        }
+        Instruction::Lop3 { data, arguments } => {
+            w.write_str("lop3.b32")?;
+            write_operands(
+                w,
+                &[
+                    &arguments.dst,
+                    &arguments.src1,
+                    &arguments.src2,
+                    &arguments.src3,
+                    data,
+                ],
+            )
+        }
+        Instruction::Shf { data, arguments } => {
+            let direction = match data.direction {
+                ShiftDirection::Left => ".l",
+                ShiftDirection::Right => ".r",
+            };
+            let mode = match data.mode {
+                VideoShiftMode::Clamp => ".clamp",
+                VideoShiftMode::Wrap => ".wrap",
+            };
+            write!(w, "shf{direction}{mode}.b32")?;
+            write_operands(
+                w,
+                &[
+                    &arguments.dst,
+                    &arguments.src1,
+                    &arguments.src2,
+                    &arguments.src3,
+                ],
+            )
+        }
+        Instruction::Sad { data, arguments } => {
+            write!(w, "sad{}", scalar_type(*data))?;
+            write_operands(
+                w,
+                &[
+                    &arguments.dst,
+                    &arguments.src1,
+                    &arguments.src2,
+                    &arguments.src3,
+                ],
+            )
+        }
+    }
+}
+
+fn write_operands<W: Write>(w: &mut W, operands: &[&dyn fmt::Display]) -> fmt::Result {
+    for (index, operand) in operands.iter().enumerate() {
+        let separator = if index == 0 { " " } else { ", " };
+        write!(w, "{separator}{operand}")?;
+    }
+    Ok(())
+}
+
+// `p|q` destinations of setp and shfl.sync, written with the leading space
+fn write_predicate_pair<W: Write, T: fmt::Display>(
+    w: &mut W,
+    first: &T,
+    second: &Option<T>,
+) -> fmt::Result {
+    write!(w, " {first}")?;
+    match second {
+        Some(second) => write!(w, "|{second}"),
+        None => Ok(()),
+    }
+}
+
+// `.weak` is the default and is left out
+fn write_qualifier<W: Write>(w: &mut W, qualifier: LdStQualifier) -> fmt::Result {
+    match qualifier {
+        LdStQualifier::Weak => Ok(()),
+        LdStQualifier::Volatile => w.write_str(".volatile"),
+        LdStQualifier::Relaxed(scope) => write!(w, ".relaxed{}", mem_scope(scope)),
+        LdStQualifier::Acquire(scope) => write!(w, ".acquire{}", mem_scope(scope)),
+        LdStQualifier::Release(scope) => write!(w, ".release{}", mem_scope(scope)),
+    }
+}
+
+fn write_arith<W: Write>(w: &mut W, opcode: &str, data: &ArithDetails) -> fmt::Result {
+    match data {
+        ArithDetails::Integer(integer) => {
+            let saturate = if integer.saturate { ".sat" } else { "" };
+            write!(w, "{opcode}{saturate}{}", scalar_type(integer.type_))
+        }
+        ArithDetails::Float(float) => write_arith_float(w, opcode, float),
+    }
+}
+
+fn write_arith_float<W: Write>(w: &mut W, opcode: &str, data: &ArithFloat) -> fmt::Result {
+    let saturate = if data.saturate { ".sat" } else { "" };
+    write!(
+        w,
+        "{opcode}{}{}{saturate}{}",
+        data.rounding.map_or("", rounding),
+        ftz(data.flush_to_zero),
+        scalar_type(data.type_)
+    )
+}
+
+fn write_setp<W: Write>(
+    w: &mut W,
+    data: &SetpData,
+    bool_op: Option<SetpBoolPostOp>,
+) -> fmt::Result {
+    // Signedness comes from the type, so `.lo` and `.lt` are both `.lt`
+    let compare = match data.cmp_op {
+        SetpCompareOp::Integer(op) => match op {
+            SetpCompareInt::Eq => ".eq",
+            SetpCompareInt::NotEq => ".ne",
+            SetpCompareInt::UnsignedLess | SetpCompareInt::SignedLess => ".lt",
+            SetpCompareInt::UnsignedLessOrEq | SetpCompareInt::SignedLessOrEq => ".le",
+            SetpCompareInt::UnsignedGreater | SetpCompareInt::SignedGreater => ".gt",
+            SetpCompareInt::UnsignedGreaterOrEq | SetpCompareInt::SignedGreaterOrEq => ".ge",
+        },
+        SetpCompareOp::Float(op) => match op {
+            SetpCompareFloat::Eq => ".eq",
+            SetpCompareFloat::NotEq => ".ne",
+            SetpCompareFloat::Less => ".lt",
+            SetpCompareFloat::LessOrEq => ".le",
+            SetpCompareFloat::Greater => ".gt",
+            SetpCompareFloat::GreaterOrEq => ".ge",
+            SetpCompareFloat::NanEq => ".equ",
+            SetpCompareFloat::NanNotEq => ".neu",
+            SetpCompareFloat::NanLess => ".ltu",
+            SetpCompareFloat::NanLessOrEq => ".leu",
+            SetpCompareFloat::NanGreater => ".gtu",
+            SetpCompareFloat::NanGreaterOrEq => ".geu",
+            SetpCompareFloat::IsNotNan => ".num",
+            SetpCompareFloat::IsAnyNan => ".nan",
+        },
+    };
+    let bool_op = match bool_op {
+        Some(SetpBoolPostOp::And) => ".and",
+        Some(SetpBoolPostOp::Or) => ".or",
+        Some(SetpBoolPostOp::Xor) => ".xor",
+        None => "",
+    };
+    write!(
+        w,
+        "setp{compare}{bool_op}{}{}",
+        ftz(data.flush_to_zero),
+        scalar_type(data.type_)
+    )
+}
+
+fn write_cvt<W: Write>(w: &mut W, data: &CvtDetails) -> fmt::Result {
+    let (rounding, flush_to_zero, relu) = match data.mode {
+        CvtMode::ZeroExtend
+        | CvtMode::SignExtend
+        | CvtMode::Truncate
+        | CvtMode::Bitcast
+        | CvtMode::SaturateUnsignedToSigned
+        | CvtMode::SaturateSignedToUnsigned => ("", None, false),
+        CvtMode::FPExtend { flush_to_zero } => ("", flush_to_zero, false),
+        CvtMode::FPTruncate {
+            rounding: mode,
+            flush_to_zero,
+            relu,
+        } => (rounding(mode), flush_to_zero, relu),
+        CvtMode::FPRound {
+            integer_rounding,
+            flush_to_zero,
+        } => (
+            integer_rounding.map_or("", int_rounding),
+            flush_to_zero,
+            false,
+        ),
+        CvtMode::SignedFromFP {
+            rounding,
+            flush_to_zero,
+        }
+        | CvtMode::UnsignedFromFP {
+            rounding,
+            flush_to_zero,
+        } => (int_rounding(rounding), flush_to_zero, false),
+        CvtMode::FPFromSigned(mode) | CvtMode::FPFromUnsigned(mode) => {
+            (rounding(mode), None, false)
+        }
+        CvtMode::Tf32 {
+            rounding,
+            relu,
+            saturate_finite,
+        } => {
+            let rounding = match rounding {
+                Tf32RoundingMode::NearestAway => ".rna",
+                Tf32RoundingMode::NearestEven => ".rn",
+                Tf32RoundingMode::Zero => ".rz",
+            };
+            let relu = if relu { ".relu" } else { "" };
+            let saturate = if saturate_finite { ".satfinite" } else { "" };
+            return write!(
+                w,
+                "cvt{rounding}{relu}{saturate}.tf32{}",
+                scalar_type(data.from)
+            );
+        }
+    };
+    let saturate = if data.saturate { ".sat" } else { "" };
+    let relu = if relu { ".relu" } else { "" };
+    write!(
+        w,
+        "cvt{rounding}{}{saturate}{relu}{}{}",
+        ftz(flush_to_zero),
+        scalar_type(data.to),
+        scalar_type(data.from)
+    )
+}
+
+fn write_min_max<W: Write>(w: &mut W, opcode: &str, data: &MinMaxDetails) -> fmt::Result {
+    match data {
+        MinMaxDetails::Signed(type_) | MinMaxDetails::Unsigned(type_) => {
+            write!(w, "{opcode}{}", scalar_type(*type_))
+        }
+        MinMaxDetails::Float(float) => {
+            let nan = if float.nan { ".NaN" } else { "" };
+            write!(
+                w,
+                "{opcode}{}{nan}{}",
+                ftz(float.flush_to_zero),
+                scalar_type(float.type_)
+            )
+        }
+    }
+}
+
+fn write_rcp<W: Write>(w: &mut W, opcode: &str, data: &RcpData) -> fmt::Result {
+    let kind = match data.kind {
+        RcpKind::Approx => ".approx",
+        RcpKind::Compliant(mode) => rounding(mode),
+    };
+    write!(
+        w,
+        "{opcode}{kind}{}{}",
+        ftz(data.flush_to_zero),
+        scalar_type(data.type_)
+    )
+}
+
+// `bar` is always aligned, only `barrier` spells it out
+fn bar_opcode(data: &BarData) -> (&'static str, &'static str) {
+    match data.spelling {
+        BarSpelling::Bar => ("bar", ""),
+        BarSpelling::Barrier if data.aligned => ("barrier", ".aligned"),
+        BarSpelling::Barrier => ("barrier", ""),
+    }
+}
+
+// `.relaxed` and `.gpu` are the defaults and are left out
+fn write_atom_prefix<W: Write>(
+    w: &mut W,
+    semantics: AtomSemantics,
+    scope: MemScope,
+    space: StateSpace,
+) -> fmt::Result {
+    let semantics = match semantics {
+        AtomSemantics::Relaxed => "",
+        AtomSemantics::Acquire => ".acquire",
+        AtomSemantics::Release => ".release",
+        AtomSemantics::AcqRel => ".acq_rel",
+    };
+    let scope = match scope {
+        MemScope::Gpu => "",
+        scope => mem_scope(scope),
+    };
+    write!(w, "atom{semantics}{scope}{}", state_space(space))
+}
+
+fn ftz(flush_to_zero: Option<bool>) -> &'static str {
+    if flush_to_zero == Some(true) {
+        ".ftz"
+    } else {
+        ""
+    }
+}
+
+fn rounding(mode: RoundingMode) -> &'static str {
+    match mode {
+        RoundingMode::NearestEven => ".rn",
+        RoundingMode::Zero => ".rz",
+        RoundingMode::NegativeInf => ".rm",
+        RoundingMode::PositiveInf => ".rp",
+    }
+}
+
+// Rounding to an integral value, used by cvt from floats
+fn int_rounding(mode: RoundingMode) -> &'static str {
+    match mode {
+        RoundingMode::NearestEven => ".rni",
+        RoundingMode::Zero => ".rzi",
+        RoundingMode::NegativeInf => ".rmi",
+        RoundingMode::PositiveInf => ".rpi",
+    }
+}
+
+fn mul_control(control: MulIntControl) -> &'static str {
+    match control {
+        MulIntControl::Low => ".lo",
+        MulIntControl::High => ".hi",
+        MulIntControl::Wide => ".wide",
+    }
+}
+
+fn mem_scope(scope: MemScope) -> &'static str {
+    match scope {
+        MemScope::Cta => ".cta",
+        MemScope::Cluster => ".cluster",
+        MemScope::Gpu => ".gpu",
+        MemScope::Sys => ".sys",
+    }
+}
+
+// Empty for the generic space, which has no modifier
+fn state_space(space: StateSpace) -> &'static str {
+    match space {
+        StateSpace::Reg => ".reg",
+        StateSpace::Generic => "",
+        StateSpace::Sreg => ".sreg",
+        StateSpace::Const => ".const",
+        StateSpace::Global => ".global",
+        StateSpace::Local => ".local",
+        StateSpace::Shared => ".shared",
+        StateSpace::SharedCta => ".shared::cta",
+        StateSpace::SharedCluster => ".shared::cluster",
+        StateSpace::Param => ".param",
+        StateSpace::ParamEntry => ".param::entry",
+        StateSpace::ParamFunc => ".param::func",
+    }
+}
+
+fn scalar_of(type_: &Type) -> ScalarType {
+    match type_ {
+        Type::Scalar(scalar) | Type::Vector(_, scalar) | Type::Array(_, scalar, _) => *scalar,
+        // Pointers are always 64 bit
+        Type::Pointer(..) => ScalarType::U64,
+    }
+}
+
+// Vector prefix and scalar type, array dimensions are written after the name
+fn type_name(type_: &Type) -> String {
+    let vector = match type_ {
+        Type::Vector(len, _) => Some(*len),
+        Type::Array(len, _, _) => len.map(|len| len.get()),
+        Type::Scalar(_) | Type::Pointer(..) => None,
+    };
+    let scalar = scalar_type(scalar_of(type_));
+    match vector {
+        Some(len) => format!(".v{len}{scalar}"),
+        None => scalar.to_string(),
+    }
+}
+
+fn scalar_type(type_: ScalarType) -> &'static str {
+    match type_ {
+        ScalarType::U8 => ".u8",
+        ScalarType::U16 => ".u16",
+        ScalarType::U16x2 => ".u16x2",
+        ScalarType::U32 => ".u32",
+        ScalarType::U64 => ".u64",
+        ScalarType::S8 => ".s8",
+        ScalarType::S16 => ".s16",
+        ScalarType::S16x2 => ".s16x2",
+        ScalarType::S32 => ".s32",
+        ScalarType::S64 => ".s64",
+        ScalarType::B8 => ".b8",
+        ScalarType::B16 => ".b16",
+        ScalarType::B32 => ".b32",
+        ScalarType::B64 => ".b64",
+        ScalarType::B128 => ".b128",
+        ScalarType::F16 => ".f16",
+        ScalarType::F16x2 => ".f16x2",
+        ScalarType::F32 => ".f32",
+        ScalarType::F64 => ".f64",
+        ScalarType::BF16 => ".bf16",
+        ScalarType::BF16x2 => ".bf16x2",
+        ScalarType::Pred => ".pred",
+    }
+}
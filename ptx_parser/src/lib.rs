@@ -4,7 +4,8 @@ use ptx_parser_macros::derive_parser;
 use rustc_hash::FxHashMap;
 use std::fmt::Debug;
 use std::iter;
-use std::num::{NonZeroU8, ParseFloatError, ParseIntError};
+use std::num::{IntErrorKind, NonZeroU8, ParseFloatError, ParseIntError};
+use std::ops::Range;
 use winnow::ascii::dec_uint;
 use winnow::combinator::*;
 use winnow::error::{ErrMode, ErrorKind};
@@ -18,7 +19,11 @@ use winnow::{
 use winnow::{prelude::*, Stateful};
 
 mod ast;
+mod emit;
+mod pass;
 pub use ast::*;
+pub use emit::*;
+pub use pass::*;
 
 impl From<RawMulIntControl> for ast::MulIntControl {
     fn from(value: RawMulIntControl) -> Self {
@@ -73,6 +78,26 @@ impl From<RawRoundingMode> for ast::RoundingMode {
     }
 }
 
+impl From<RawVideoShiftMode> for ast::VideoShiftMode {
+    fn from(value: RawVideoShiftMode) -> Self {
+        match value {
+            RawVideoShiftMode::Clamp => ast::VideoShiftMode::Clamp,
+            RawVideoShiftMode::Wrap => ast::VideoShiftMode::Wrap,
+        }
+    }
+}
+
+impl From<RawShuffleMode> for ast::ShuffleMode {
+    fn from(value: RawShuffleMode) -> Self {
+        match value {
+            RawShuffleMode::Up => ast::ShuffleMode::Up,
+            RawShuffleMode::Down => ast::ShuffleMode::Down,
+            RawShuffleMode::BFly => ast::ShuffleMode::BFly,
+            RawShuffleMode::Idx => ast::ShuffleMode::Idx,
+        }
+    }
+}
+
 impl VectorPrefix {
     pub(crate) fn len(self) -> NonZeroU8 {
         unsafe {
@@ -89,6 +114,18 @@ struct PtxParserState<'a, 'input> {
     errors: &'a mut Vec<PtxError>,
     function_declarations:
         FxHashMap<&'input str, (Vec<(ast::Type, StateSpace)>, Vec<(ast::Type, StateSpace)>)>,
+    // Symbol tables used for type checks during parsing
+    global_variables: FxHashMap<&'input str, VariableInfo>,
+    local_variables: FxHashMap<&'input str, VariableInfo>,
+    options: ParseOptions,
+}
+
+struct VariableInfo {
+    type_: ast::Type,
+    state_space: StateSpace,
+    // Only set for parameterized declarations like `.reg .b32 %r<10>;`
+    count: Option<u32>,
+    align: Option<u32>,
 }
 
 impl<'a, 'input> PtxParserState<'a, 'input> {
@@ -96,6 +133,76 @@ impl<'a, 'input> PtxParserState<'a, 'input> {
         Self {
             errors,
             function_declarations: FxHashMap::default(),
+            global_variables: FxHashMap::default(),
+            local_variables: FxHashMap::default(),
+            options: ParseOptions::default(),
+        }
+    }
+
+    fn record_variable(&mut self, global: bool, var: &Variable<&'input str>, count: Option<u32>) {
+        let variables = if global {
+            &mut self.global_variables
+        } else {
+            &mut self.local_variables
+        };
+        variables.insert(
+            var.name,
+            VariableInfo {
+                type_: var.v_type.clone(),
+                state_space: var.state_space,
+                count,
+                align: var.align,
+            },
+        );
+    }
+
+    fn variable(&self, name: &str) -> Option<&VariableInfo> {
+        Self::lookup(&self.local_variables, name).or_else(|| Self::lookup(&self.global_variables, name))
+    }
+
+    fn variable_type(&self, name: &str) -> Option<(&ast::Type, StateSpace)> {
+        self.variable(name).map(|var| (&var.type_, var.state_space))
+    }
+
+    fn is_symbol(&self, name: &str) -> bool {
+        self.function_declarations.contains_key(name)
+            || matches!(self.variable_type(name), Some((_, space)) if space != StateSpace::Reg)
+    }
+
+    fn operand_has_type(&self, operand: &ParsedOperandStr, type_: &ast::Type) -> bool {
+        let has_type = |name: &str, type_: &ast::Type| match self.variable_type(name) {
+            Some((var_type, _)) => var_type == type_,
+            None => true,
+        };
+        match (operand, type_) {
+            (ast::ParsedOperand::Reg(name), _) => has_type(name, type_),
+            (ast::ParsedOperand::VecPack(elements), ast::Type::Vector(len, scalar)) => {
+                elements.len() == *len as usize
+                    && elements
+                        .iter()
+                        .all(|name| has_type(name, &ast::Type::Scalar(*scalar)))
+            }
+            (ast::ParsedOperand::VecPack(_), _) => false,
+            _ => true,
+        }
+    }
+
+    fn lookup<'x>(
+        variables: &'x FxHashMap<&'input str, VariableInfo>,
+        name: &str,
+    ) -> Option<&'x VariableInfo> {
+        if let Some(var @ VariableInfo { count: None, .. }) = variables.get(name) {
+            return Some(var);
+        }
+        // %r12 is an instance of a parameterized declaration %r<N>
+        let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+        if prefix.len() == name.len() {
+            return None;
+        }
+        let index = name[prefix.len()..].parse::<u32>().ok()?;
+        match variables.get(prefix) {
+            Some(var @ VariableInfo { count: Some(count), .. }) if index < *count => Some(var),
+            _ => None,
         }
     }
 
@@ -129,6 +236,18 @@ impl<'a, 'input> Debug for PtxParserState<'a, 'input> {
 
 type PtxParser<'a, 'input> = Stateful<&'a [Token<'input>], PtxParserState<'a, 'input>>;
 
+// String literal without the quotes
+fn string<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<&'input str> {
+    any.verify_map(|t| {
+        if let Token::String(text) = t {
+            Some(&text[1..text.len() - 1])
+        } else {
+            None
+        }
+    })
+    .parse_next(stream)
+}
+
 fn ident<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<&'input str> {
     any.verify_map(|t| {
         if let Token::Ident(text) = t {
@@ -239,12 +358,30 @@ fn f64<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<f64> {
     .parse_next(stream)
 }
 
+// Overflow gets its own error, malformed digits are reported as they are
+fn int_literal_error(err: ParseIntError, text: &str, expected_type: &'static str) -> PtxError {
+    match err.kind() {
+        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => PtxError::LiteralOutOfRange {
+            value: text.to_string(),
+            expected_type,
+        },
+        _ => PtxError::from(err),
+    }
+}
+
 fn s32<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<i32> {
     take_error((opt(Token::Minus), num).map(|(sign, x)| {
         let (text, radix, _) = x;
-        match i32::from_str_radix(text, radix) {
-            Ok(x) => Ok(if sign.is_some() { -x } else { x }),
-            Err(err) => Err((0, PtxError::from(err))),
+        // The magnitude is parsed separately from the sign, so it must be wide
+        // enough to hold -i32::MIN
+        let out_of_range = || PtxError::LiteralOutOfRange {
+            value: format!("{}{}", if sign.is_some() { "-" } else { "" }, text),
+            expected_type: "s32",
+        };
+        match i64::from_str_radix(text, radix) {
+            Ok(x) => i32::try_from(if sign.is_some() { -x } else { x })
+                .map_err(|_| (0, out_of_range())),
+            Err(err) => Err((0, int_literal_error(err, text, "s32"))),
         }
     }))
     .parse_next(stream)
@@ -255,7 +392,7 @@ fn u8<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<u8> {
         let (text, radix, _) = x;
         match u8::from_str_radix(text, radix) {
             Ok(x) => Ok(x),
-            Err(err) => Err((0, PtxError::from(err))),
+            Err(err) => Err((0, int_literal_error(err, text, "u8"))),
         }
     }))
     .parse_next(stream)
@@ -266,7 +403,7 @@ fn u32<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<u32> {
         let (text, radix, _) = x;
         match u32::from_str_radix(text, radix) {
             Ok(x) => Ok(x),
-            Err(err) => Err((0, PtxError::from(err))),
+            Err(err) => Err((0, int_literal_error(err, text, "u32"))),
         }
     }))
     .parse_next(stream)
@@ -281,6 +418,46 @@ fn immediate_value<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<as
     .parse_next(stream)
 }
 
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ParseOptions {
+    // Unsupported modifiers are reported as `PtxError::Todo`. By default they
+    // do not prevent the module from being returned, in strict mode they are
+    // fatal and the offending instruction is dropped
+    pub strict_unsupported: bool,
+    // `sm_XX` the module will be compiled for. Instructions that need a newer
+    // target are reported as `PtxError::UnsupportedArch`. When unset, every
+    // instruction is accepted
+    pub target_arch: Option<u32>,
+}
+
+impl ParseOptions {
+    fn is_fatal(&self, err: &PtxError) -> bool {
+        match err {
+            PtxError::Todo => self.strict_unsupported,
+            err => !err.is_warning(),
+        }
+    }
+}
+
+// Tokens of `text` together with their byte ranges, for tools like syntax
+// highlighters. Stops at the first invalid token
+pub fn tokens<'input>(
+    text: &'input str,
+) -> Result<Vec<(Token<'input>, Range<usize>)>, TokenError> {
+    let mut lexer = Token::lexer(text);
+    let mut result = Vec::new();
+    while let Some(maybe_token) = lexer.next() {
+        match maybe_token {
+            Ok(token) => result.push((token, lexer.span())),
+            Err(mut err) => {
+                err.0 = lexer.span();
+                return Err(err);
+            }
+        }
+    }
+    Ok(result)
+}
+
 pub fn parse_module_unchecked<'input>(text: &'input str) -> Option<ast::Module<'input>> {
     let lexer = Token::lexer(text);
     let input = lexer.collect::<Result<Vec<_>, _>>().ok()?;
@@ -291,16 +468,69 @@ pub fn parse_module_unchecked<'input>(text: &'input str) -> Option<ast::Module<'
         input: &input[..],
     };
     let parsing_result = module.parse(parser).ok();
-    if !errors.is_empty() {
+    if errors.iter().any(|err| !err.is_warning()) {
         None
     } else {
         parsing_result
     }
 }
 
+// Unlike `parse_module`, every error that is not a warning is fatal here,
+// unsupported modifiers included
 pub fn parse_module_checked<'input>(
     text: &'input str,
 ) -> Result<ast::Module<'input>, Vec<PtxError>> {
+    let result = parse_module(text);
+    if result.errors.iter().any(|err| !err.is_warning()) {
+        return Err(result.errors);
+    }
+    result.into_result()
+}
+
+// The module is only returned if there were no fatal errors, `errors` holds
+// all errors and warnings encountered during parsing
+pub struct ParseResult<'input> {
+    pub module: Option<ast::Module<'input>>,
+    pub errors: Vec<PtxError>,
+    // Tokens produced by the lexer, 0 if it failed
+    pub token_count: usize,
+}
+
+// The AST does not implement Debug, so only report whether there is a module
+impl<'input> Debug for ParseResult<'input> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParseResult")
+            .field("has_module", &self.module.is_some())
+            .field("errors", &self.errors)
+            .field("token_count", &self.token_count)
+            .finish()
+    }
+}
+
+impl<'input> ParseResult<'input> {
+    // Lexer and parser failures leave no module either, so they count as
+    // fatal errors too
+    pub fn has_fatal_errors(&self) -> bool {
+        self.module.is_none()
+    }
+
+    // Warnings are dropped when the module is returned
+    pub fn into_result(self) -> Result<ast::Module<'input>, Vec<PtxError>> {
+        match self.module {
+            Some(module) => Ok(module),
+            None => Err(self.errors),
+        }
+    }
+}
+
+pub fn parse_module<'input>(text: &'input str) -> ParseResult<'input> {
+    parse_module_with_options(text, ParseOptions::default())
+}
+
+pub fn parse_module_with_options<'input>(
+    text: &'input str,
+    options: ParseOptions,
+) -> ParseResult<'input> {
     let mut lexer = Token::lexer(text);
     let mut errors = Vec::new();
     let mut tokens = Vec::new();
@@ -317,21 +547,83 @@ pub fn parse_module_checked<'input>(
             }
         }
     }
+    if !errors.is_empty() {
+        return ParseResult {
+            module: None,
+            errors,
+            token_count: 0,
+        };
+    }
+    let parse_result = {
+        let mut state = PtxParserState::new(&mut errors);
+        state.options = options;
+        let parser = PtxParser {
+            state,
+            input: &tokens[..],
+        };
+        module
+            .parse(parser)
+            .map_err(|err| PtxError::Parser(err.into_inner()))
+    };
+    let module = match parse_result {
+        Ok(result) if !errors.iter().any(|err| options.is_fatal(err)) => Some(result),
+        Ok(_) => None,
+        Err(err) => {
+            errors.push(err);
+            None
+        }
+    };
+    ParseResult {
+        module,
+        errors,
+        token_count: tokens.len(),
+    }
+}
+
+// Parses a single function, `span` is its location in `text` (linking
+// directives included). Meant for editors that only reparse the function that
+// changed. Module-level declarations are not visible, so uses of globals and
+// calls to other functions are not checked
+pub fn reparse_function<'input>(
+    text: &'input str,
+    span: Range<usize>,
+) -> Result<
+    ast::Function<'input, &'input str, ast::Statement<ParsedOperandStr<'input>>>,
+    Vec<PtxError>,
+> {
+    // Out of bounds or not on a char boundary
+    let function_text = text
+        .get(span.clone())
+        .ok_or_else(|| vec![PtxError::from(TokenError(span.clone()))])?;
+    let mut lexer = Token::lexer(function_text);
+    let mut errors = Vec::new();
+    let mut tokens = Vec::new();
+    while let Some(maybe_token) = lexer.next() {
+        match maybe_token {
+            Ok(token) => tokens.push(token),
+            Err(mut err) => {
+                let token_span = lexer.span();
+                err.0 = span.start + token_span.start..span.start + token_span.end;
+                errors.push(PtxError::from(err))
+            }
+        }
+    }
     if !errors.is_empty() {
         return Err(errors);
     }
+    let options = ParseOptions::default();
     let parse_result = {
         let state = PtxParserState::new(&mut errors);
         let parser = PtxParser {
             state,
             input: &tokens[..],
         };
-        module
+        terminated(function, eof)
             .parse(parser)
             .map_err(|err| PtxError::Parser(err.into_inner()))
     };
     match parse_result {
-        Ok(result) if errors.is_empty() => Ok(result),
+        Ok((_, func)) if !errors.iter().any(|err| options.is_fatal(err)) => Ok(func),
         Ok(_) => Err(errors),
         Err(err) => {
             errors.push(err);
@@ -348,23 +640,45 @@ fn module<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<ast::Module
         repeat_without_none(directive),
         eof,
     )
-        .map(|(version, _, _, directives, _)| ast::Module {
+        .map(|(version, target, _, directives, _)| ast::Module {
             version,
+            target,
             directives,
         })
         .parse_next(stream)
 }
 
 fn address_size<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<()> {
-    (Token::DotAddressSize, u8_literal(64))
-        .void()
-        .parse_next(stream)
+    let size = preceded(Token::DotAddressSize, u8.verify(|size| *size == 32 || *size == 64))
+        .parse_next(stream)?;
+    // Pointer sizes (`Type::size_of_bytes`, `cvta`, linking) assume 64 bit
+    // addresses
+    if size != 64 {
+        stream.state.errors.push(PtxError::Unsupported32Bit);
+    }
+    Ok(())
 }
 
+// Newest minor version of each PTX ISA major version we know of, starting
+// from 1.x. Newer modules are still parsed, but they may use syntax we don't
+// understand, so we emit a warning
+const MAX_PTX_MINOR_VERSIONS: [u8; 8] = [5, 3, 2, 3, 0, 5, 8, 8];
+
 fn version<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<(u8, u8)> {
-    (Token::DotVersion, u8, Token::Dot, u8)
+    let (major, minor) = (Token::DotVersion, u8, Token::Dot, u8)
         .map(|(_, major, _, minor)| (major, minor))
-        .parse_next(stream)
+        .parse_next(stream)?;
+    let known = (major as usize)
+        .checked_sub(1)
+        .and_then(|index| MAX_PTX_MINOR_VERSIONS.get(index))
+        .is_some_and(|max_minor| minor <= *max_minor);
+    if !known {
+        stream
+            .state
+            .errors
+            .push(PtxError::UnsupportedVersion { major, minor });
+    }
+    Ok((major, minor))
 }
 
 fn target<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<(u32, Option<char>)> {
@@ -389,6 +703,7 @@ fn directive<'a, 'input>(
         function.map(|(linking, func)| Some(ast::Directive::Method(linking, func))),
         file.map(|_| None),
         section.map(|_| None),
+        dwarf_line.map(|line| Some(ast::Directive::DwarfLine(line))),
         (module_variable, Token::Semicolon)
             .map(|((linking, var), _)| Some(ast::Directive::Variable(linking, var))),
     ))
@@ -400,24 +715,54 @@ fn module_variable<'a, 'input>(
 ) -> PResult<(ast::LinkingDirective, ast::Variable<&'input str>)> {
     let linking = linking_directives.parse_next(stream)?;
     let var = global_space
-        .flat_map(|space| multi_variable(linking.contains(LinkingDirective::EXTERN), space))
+        .flat_map(|space| {
+            scoped_variable(true, linking.contains(LinkingDirective::EXTERN), space)
+        })
         // TODO: support multi var in globals
         .map(|multi_var| multi_var.var)
         .parse_next(stream)?;
     Ok((linking, var))
 }
 
+// Shared by module-scope directives and function-body statements, so that
+// e.g. a `.shared` array is parsed and recorded the same way in both places
+fn scoped_variable<'a, 'input: 'a>(
+    global: bool,
+    extern_: bool,
+    state_space: StateSpace,
+) -> impl Parser<PtxParser<'a, 'input>, MultiVariable<&'input str>, ContextError> {
+    move |stream: &mut PtxParser<'a, 'input>| {
+        let var = multi_variable(extern_, state_space).parse_next(stream)?;
+        check_align(&mut stream.state, var.var.align);
+        stream.state.record_variable(global, &var.var, var.count);
+        Ok(var)
+    }
+}
+
 fn file<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<()> {
     (
         Token::DotFile,
         u32,
-        Token::String,
+        string,
         opt((Token::Comma, u32, Token::Comma, u32)),
     )
         .void()
         .parse_next(stream)
 }
 
+// Older toolchains emit debug information as `@@DWARF <data>` lines instead of
+// `.section` blocks. We keep the text following the prefix
+fn dwarf_line<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<&'input str> {
+    any.verify_map(|t| {
+        if let Token::DwarfLine(text) = t {
+            Some(text["@@DWARF".len()..].trim())
+        } else {
+            None
+        }
+    })
+    .parse_next(stream)
+}
+
 fn section<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<()> {
     (
         Token::DotSection.void(),
@@ -460,27 +805,258 @@ fn function<'a, 'input>(
     ast::LinkingDirective,
     ast::Function<'input, &'input str, ast::Statement<ParsedOperand<&'input str>>>,
 )> {
-    let (linking, function) = (
-        linking_directives,
-        method_declaration,
-        repeat(0.., tuning_directive),
-        function_body,
-    )
-        .map(|(linking, func_directive, tuning, body)| {
-            (
-                linking,
-                ast::Function {
-                    func_directive,
-                    tuning,
-                    body,
-                },
-            )
-        })
-        .parse_next(stream)?;
+    let linking = linking_directives.parse_next(stream)?;
+    let mut tuning: Vec<_> = repeat(0.., tuning_directive).parse_next(stream)?;
+    let func_directive = method_declaration.parse_next(stream)?;
+    tuning.extend::<Vec<_>>(repeat(0.., tuning_directive).parse_next(stream)?);
+    stream.state.local_variables.clear();
+    for var in func_directive
+        .return_arguments
+        .iter()
+        .chain(func_directive.input_arguments.iter())
+    {
+        stream.state.record_variable(false, var, None);
+    }
+    let body = function_body.parse_next(stream)?;
+    if let Some(ref body) = body {
+        check_param_accesses(&mut stream.state, &func_directive.return_arguments, body);
+        check_access_alignment(&mut stream.state, body);
+        check_divergence(&mut stream.state, body);
+    }
+    let function = ast::Function {
+        func_directive,
+        tuning,
+        body,
+    };
     stream.state.record_function(&function.func_directive);
     Ok((linking, function))
 }
 
+// .param space is only used for passing arguments: reads of declared
+// parameters, writes to our return value and writes to call arguments that
+// are followed by a call. Anything else is likely a broken ABI lowering, but
+// it's not necessarily wrong, so we emit a warning
+fn check_param_accesses(
+    state: &mut PtxParserState,
+    return_arguments: &[ast::Variable<&str>],
+    body: &[ast::Statement<ParsedOperandStr>],
+) {
+    fn param_name<'x>(operand: &ParsedOperandStr<'x>) -> Option<&'x str> {
+        match operand {
+            ast::ParsedOperand::Reg(name) | ast::ParsedOperand::RegOffset(name, _) => Some(name),
+            _ => None,
+        }
+    }
+    for (index, statement) in body.iter().enumerate() {
+        let (name, is_store) = match statement {
+            ast::Statement::Block(block) => {
+                check_param_accesses(state, return_arguments, block);
+                continue;
+            }
+            ast::Statement::Instruction(_, ast::Instruction::Ld { data, arguments })
+                if data.state_space == StateSpace::Param =>
+            {
+                (param_name(&arguments.src), false)
+            }
+            ast::Statement::Instruction(_, ast::Instruction::St { data, arguments })
+                if data.state_space == StateSpace::Param =>
+            {
+                (param_name(&arguments.src1), true)
+            }
+            _ => continue,
+        };
+        let declared = match name.and_then(|name| state.variable_type(name)) {
+            Some((_, space)) => space == StateSpace::Param,
+            None => false,
+        };
+        let valid = declared
+            && (!is_store
+                || return_arguments.iter().any(|arg| Some(arg.name) == name)
+                || body[index + 1..].iter().any(|statement| {
+                    matches!(
+                        statement,
+                        ast::Statement::Instruction(_, ast::Instruction::Call { .. })
+                    )
+                }));
+        if !valid {
+            state.errors.push(PtxError::UnexpectedParamAccess);
+        }
+    }
+}
+
+// Memory accesses must be naturally aligned: 8 bytes for .u64, 16 bytes for
+// .b128 or .v4.b32, etc. We can only tell when the address is a variable with
+// a known alignment, the alignment of a register address is unknown until
+// runtime. Of the atomics, only packed half ones are checked
+fn check_access_alignment(state: &mut PtxParserState, body: &[ast::Statement<ParsedOperandStr>]) {
+    for statement in body {
+        let (type_, address) = match statement {
+            ast::Statement::Block(block) => {
+                check_access_alignment(state, block);
+                continue;
+            }
+            ast::Statement::Instruction(_, ast::Instruction::Ld { data, arguments }) => {
+                (&data.typ, &arguments.src)
+            }
+            ast::Statement::Instruction(_, ast::Instruction::St { data, arguments }) => {
+                (&data.typ, &arguments.src1)
+            }
+            ast::Statement::Instruction(_, ast::Instruction::Atom { data, arguments })
+                if matches!(
+                    data.type_,
+                    ast::Type::Scalar(ScalarType::F16x2 | ScalarType::BF16x2)
+                        | ast::Type::Vector(_, ScalarType::F16x2 | ScalarType::BF16x2)
+                ) =>
+            {
+                (&data.type_, &arguments.src1)
+            }
+            _ => continue,
+        };
+        check_address_alignment(state, address, type_size(type_));
+    }
+}
+
+// `bra.uni` promises that the whole warp takes the branch together and
+// `bar.sync` must be reached by all threads of the block. We warn when a
+// guard obviously depends on the thread: it's computed from `%tid` or
+// `%laneid`, directly or through other registers. Anything else is assumed to
+// be uniform
+fn check_divergence<'input>(
+    state: &mut PtxParserState,
+    body: &[ast::Statement<ParsedOperandStr<'input>>],
+) {
+    fn flatten<'s, 'input>(
+        body: &'s [ast::Statement<ParsedOperandStr<'input>>],
+        result: &mut Vec<&'s ast::Statement<ParsedOperandStr<'input>>>,
+    ) {
+        for statement in body {
+            match statement {
+                ast::Statement::Block(block) => flatten(block, result),
+                statement @ (ast::Statement::Instruction(..) | ast::Statement::Label(_)) => {
+                    result.push(statement)
+                }
+                _ => {}
+            }
+        }
+    }
+    let mut statements = Vec::new();
+    flatten(body, &mut statements);
+    let mut divergent: Vec<&'input str> = vec!["%tid", "%laneid"];
+    // Loops can carry divergence backwards, so iterate until nothing changes
+    loop {
+        let known = divergent.len();
+        for statement in statements.iter() {
+            let (guard, instruction) = match statement {
+                ast::Statement::Instruction(guard, instruction) => (guard, instruction),
+                _ => continue,
+            };
+            let mut reads_divergent = guard
+                .as_ref()
+                .is_some_and(|guard| divergent.contains(&guard.label));
+            let mut written = Vec::new();
+            let _ = ast::visit(
+                instruction,
+                &mut |operand: &ParsedOperandStr<'input>,
+                      _: Option<(&ast::Type, StateSpace)>,
+                      is_dst: bool,
+                      _: bool| {
+                    let names = match operand {
+                        ast::ParsedOperand::Reg(name)
+                        | ast::ParsedOperand::RegOffset(name, _)
+                        | ast::ParsedOperand::SymbolOffset(name, _)
+                        | ast::ParsedOperand::VecMember(name, _)
+                        | ast::ParsedOperand::SpecialRegisterDimension(name, _)
+                        | ast::ParsedOperand::Generic(name) => std::slice::from_ref(name),
+                        ast::ParsedOperand::VecPack(names) => &names[..],
+                        ast::ParsedOperand::Imm(_) => &[],
+                    };
+                    if is_dst {
+                        written.extend_from_slice(names);
+                    } else {
+                        reads_divergent |= names.iter().any(|name| divergent.contains(name));
+                    }
+                    Ok::<_, std::convert::Infallible>(())
+                },
+            );
+            if reads_divergent {
+                for name in written {
+                    if !divergent.contains(&name) {
+                        divergent.push(name);
+                    }
+                }
+            }
+        }
+        if divergent.len() == known {
+            break;
+        }
+    }
+    let is_divergent = |guard: &Option<ast::PredAt<&str>>| {
+        guard
+            .as_ref()
+            .is_some_and(|guard| divergent.contains(&guard.label))
+    };
+    // A barrier is also divergent between a divergent forward branch and its
+    // target, some threads of the warp jumped over it
+    let mut labels = Vec::new();
+    let mut skipped_to = Vec::new();
+    for statement in statements {
+        match statement {
+            ast::Statement::Label(label) => {
+                labels.push(*label);
+                skipped_to.retain(|target| target != label);
+            }
+            ast::Statement::Instruction(guard, ast::Instruction::Bra { data, arguments })
+                if is_divergent(guard) =>
+            {
+                if data.uniform {
+                    state.errors.push(PtxError::DivergentUniformBranch);
+                } else if !labels.contains(&arguments.src) {
+                    skipped_to.push(arguments.src);
+                }
+            }
+            ast::Statement::Instruction(guard, ast::Instruction::Bar { .. })
+                if is_divergent(guard) || !skipped_to.is_empty() =>
+            {
+                state.errors.push(PtxError::DivergentBarrier);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn check_address_alignment(
+    state: &mut PtxParserState,
+    address: &ParsedOperandStr,
+    required_alignment: u32,
+) {
+    let (name, offset) = match address {
+        ast::ParsedOperand::Reg(name) => (*name, 0),
+        ast::ParsedOperand::RegOffset(name, offset) => (*name, *offset),
+        _ => return,
+    };
+    let align = match state.variable(name) {
+        Some(var) if var.state_space != StateSpace::Reg => {
+            var.align.unwrap_or_else(|| type_size(&var.type_))
+        }
+        _ => return,
+    };
+    if align % required_alignment != 0 || offset % required_alignment as i32 != 0 {
+        state.errors.push(PtxError::MisalignedAccess);
+    }
+}
+
+// Size of a single element, for arrays this is also their natural alignment
+pub(crate) fn type_size(type_: &ast::Type) -> u32 {
+    match type_ {
+        ast::Type::Scalar(scalar) => scalar.size_of() as u32,
+        ast::Type::Vector(len, scalar) => *len as u32 * scalar.size_of() as u32,
+        ast::Type::Array(vector, scalar, _) => {
+            vector.map_or(1, |len| len.get() as u32) * scalar.size_of() as u32
+        }
+        ast::Type::Pointer(..) => 8,
+    }
+}
+
 fn linking_directives<'a, 'input>(
     stream: &mut PtxParser<'a, 'input>,
 ) -> PResult<ast::LinkingDirective> {
@@ -517,7 +1093,7 @@ fn method_declaration<'a, 'input>(
         Token::DotEntry => (ident, kernel_arguments).map(|(name, input_arguments)| ast::MethodDeclaration{
             return_arguments: Vec::new(), name: ast::MethodName::Kernel(name), input_arguments, shared_mem: None
         }),
-        Token::DotFunc => (opt(fn_arguments), ident, fn_arguments).map(|(return_arguments, name,input_arguments)| {
+        Token::DotFunc => (opt(fn_return_arguments), ident, fn_arguments).map(|(return_arguments, name,input_arguments)| {
             let return_arguments = return_arguments.unwrap_or_else(|| Vec::new());
             let name = ast::MethodName::Func(name);
             ast::MethodDeclaration{ return_arguments, name, input_arguments, shared_mem: None }
@@ -538,6 +1114,27 @@ fn fn_arguments<'a, 'input>(
     .parse_next(stream)
 }
 
+fn fn_return_arguments<'a, 'input>(
+    stream: &mut PtxParser<'a, 'input>,
+) -> PResult<Vec<ast::Variable<&'input str>>> {
+    delimited(
+        Token::LParen,
+        separated(0.., fn_return, Token::Comma),
+        Token::RParen,
+    )
+    .parse_next(stream)
+}
+
+// Values are only returned through registers or the parameter space, other
+// spaces are parsed to report an error instead of failing the whole function
+fn fn_return<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<ast::Variable<&'input str>> {
+    let state_space = method_space.parse_next(stream)?;
+    if !matches!(state_space, StateSpace::Param | StateSpace::Reg) {
+        stream.state.errors.push(PtxError::SyntaxError);
+    }
+    method_parameter(state_space).parse_next(stream)
+}
+
 fn kernel_arguments<'a, 'input>(
     stream: &mut PtxParser<'a, 'input>,
 ) -> PResult<Vec<ast::Variable<&'input str>>> {
@@ -619,23 +1216,31 @@ fn statement<'a, 'input>(
     alt((
         label.map(Some),
         debug_directive.map(|_| None),
-        terminated(
-            method_space
-                .flat_map(|space| multi_variable(false, space))
-                .map(|var| Some(Statement::Variable(var))),
-            Token::Semicolon,
-        ),
-        predicated_instruction.map(Some),
-        pragma.map(|_| None),
+        variable_statement.map(|var| Some(Statement::Variable(var))),
+        predicated_instruction,
+        pragma.map(|args| Some(Statement::Pragma(args))),
         block_statement.map(Some),
     ))
     .parse_next(stream)
 }
 
-fn pragma<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<()> {
-    (Token::DotPragma, Token::String, Token::Semicolon)
-        .void()
-        .parse_next(stream)
+fn variable_statement<'a, 'input>(
+    stream: &mut PtxParser<'a, 'input>,
+) -> PResult<ast::MultiVariable<&'input str>> {
+    terminated(
+        method_space.flat_map(|space| scoped_variable(false, false, space)),
+        Token::Semicolon,
+    )
+    .parse_next(stream)
+}
+
+fn pragma<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<Vec<String>> {
+    delimited(
+        Token::DotPragma,
+        separated(1.., string.map(str::to_string), Token::Comma),
+        Token::Semicolon,
+    )
+    .parse_next(stream)
 }
 
 fn method_parameter<'a, 'input: 'a>(
@@ -648,6 +1253,8 @@ fn method_parameter<'a, 'input: 'a>(
         } else {
             None
         };
+        check_array_declaration(&mut stream.state, state_space, type_, &array_dimensions);
+        check_align(&mut stream.state, align);
         // TODO: push this check into array_dimensions(...)
         if let Some(ref dims) = array_dimensions {
             if dims[0] == 0 {
@@ -669,7 +1276,7 @@ fn variable_declaration<'a, 'input>(
     stream: &mut PtxParser<'a, 'input>,
 ) -> PResult<(Option<u32>, Option<NonZeroU8>, ScalarType, &'input str)> {
     (
-        opt(align.verify(|x| x.count_ones() == 1)),
+        opt(align),
         vector_prefix,
         scalar_type,
         ident,
@@ -700,11 +1307,8 @@ fn multi_variable<'a, 'input: 'a>(
                 count,
             });
         }
-        let mut array_dimensions = if state_space != StateSpace::Reg {
-            opt(array_dimensions).parse_next(stream)?
-        } else {
-            None
-        };
+        let mut array_dimensions = opt(array_dimensions).parse_next(stream)?;
+        check_array_declaration(&mut stream.state, state_space, type_, &array_dimensions);
         let initializer = match state_space {
             StateSpace::Global | StateSpace::Const => match array_dimensions {
                 Some(ref mut dimensions) => {
@@ -714,9 +1318,11 @@ fn multi_variable<'a, 'input: 'a>(
             },
             _ => None,
         };
+        // Unsized arrays (`.b8 foo[]`) are only valid when the storage is
+        // provided elsewhere, e.g. dynamic shared memory
         if let Some(ref dims) = array_dimensions {
             if !extern_ && dims[0] == 0 {
-                return Err(ErrMode::from_error_kind(stream, ErrorKind::Verify));
+                stream.state.errors.push(PtxError::NonExternPointer);
             }
         }
         Ok(MultiVariable {
@@ -732,6 +1338,38 @@ fn multi_variable<'a, 'input: 'a>(
     }
 }
 
+// `.align` must be a power of two. Checked once the whole declaration is
+// parsed rather than in `align`, which alternatives may run more than once
+fn check_align(state: &mut PtxParserState, align: Option<u32>) {
+    if align.is_some_and(|align| !align.is_power_of_two()) {
+        state.errors.push(PtxError::SyntaxError);
+    }
+}
+
+// Only memory state spaces can hold multi-dimensional arrays. Predicates have
+// no in-memory representation, so they can't be array elements
+fn check_array_declaration(
+    state: &mut PtxParserState,
+    state_space: StateSpace,
+    type_: ScalarType,
+    dims: &Option<Vec<u32>>,
+) {
+    let dims = match dims {
+        Some(dims) => dims,
+        None => return,
+    };
+    if dims.len() > 1 && matches!(state_space, StateSpace::Reg | StateSpace::Param) {
+        state.errors.push(PtxError::MultiArrayVariable);
+    } else if state_space == StateSpace::Reg {
+        // Registers can't be arrays, dimensions are only parsed to report
+        // a specific error
+        state.errors.push(PtxError::RegisterArray);
+    }
+    if type_ == ScalarType::Pred {
+        state.errors.push(PtxError::WrongArrayType);
+    }
+}
+
 fn array_initializer<'a, 'input: 'a>(
     vector: Option<NonZeroU8>,
     type_: ScalarType,
@@ -744,18 +1382,18 @@ fn array_initializer<'a, 'input: 'a>(
         if vector.is_some() || array_dimensions[0] == 0 || array_dimensions.len() > 1 {
             return Err(ErrMode::from_error_kind(stream, ErrorKind::Verify));
         }
-        delimited(
+        let count: usize = delimited(
             Token::LBrace,
-            separated(
-                0..=array_dimensions[0] as usize,
-                single_value_append(&mut result, type_),
-                Token::Comma,
-            ),
+            separated(0.., single_value_append(&mut result, type_), Token::Comma),
             Token::RBrace,
         )
         .parse_next(stream)?;
-        // pad with zeros
         let result_size = type_.size_of() as usize * array_dimensions[0] as usize;
+        if count > array_dimensions[0] as usize {
+            stream.state.errors.push(PtxError::ArrayInitalizer);
+            result.truncate(result_size);
+        }
+        // pad with zeros
         result.extend(iter::repeat(0u8).take(result_size - result.len()));
         Ok(result)
     }
@@ -832,6 +1470,12 @@ fn single_value_append<'a, 'input: 'a>(
             (ScalarType::S64, ImmediateValue::S64(x)) => {
                 accumulator.extend_from_slice(&(x as i64).to_le_bytes())
             }
+            (ScalarType::F16, ImmediateValue::F32(x)) => {
+                accumulator.extend_from_slice(&f32_to_f16_bits(x).to_le_bytes())
+            }
+            (ScalarType::BF16, ImmediateValue::F32(x)) => {
+                accumulator.extend_from_slice(&f32_to_bf16_bits(x).to_le_bytes())
+            }
             (ScalarType::F32, ImmediateValue::F32(x)) => {
                 accumulator.extend_from_slice(&x.to_le_bytes())
             }
@@ -844,20 +1488,50 @@ fn single_value_append<'a, 'input: 'a>(
     }
 }
 
+// Both conversions round to nearest, ties to even. NaNs stay quiet NaNs
+fn f32_to_f16_bits(x: f32) -> u16 {
+    let bits = x.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    // Subnormal results keep the implicit leading bit in the mantissa
+    let (half, shift) = if exponent > 0 {
+        (((exponent as u32) << 10) | (mantissa >> 13), 13)
+    } else if exponent >= -10 {
+        let shift = (14 - exponent) as u32;
+        ((mantissa | 0x80_0000) >> shift, shift)
+    } else {
+        return sign;
+    };
+    let remainder = (mantissa | 0x80_0000) & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    let round_up = remainder > halfway || (remainder == halfway && half & 1 == 1);
+    // A carry out of the mantissa correctly bumps the exponent, up to infinity
+    sign | (half + round_up as u32) as u16
+}
+
+fn f32_to_bf16_bits(x: f32) -> u16 {
+    let bits = x.to_bits();
+    if x.is_nan() {
+        return (bits >> 16) as u16 | 0x40;
+    }
+    ((bits + 0x7fff + ((bits >> 16) & 1)) >> 16) as u16
+}
+
+// The first dimension can be left empty (`[]`), we represent it as 0
 fn array_dimensions<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<Vec<u32>> {
-    let dimension = delimited(
-        Token::LBracket,
-        opt(u32).verify(|dim| *dim != Some(0)),
-        Token::RBracket,
-    )
-    .parse_next(stream)?;
+    let dimension = delimited(Token::LBracket, opt(array_dimension), Token::RBracket)
+        .parse_next(stream)?;
     let result = vec![dimension.unwrap_or(0)];
     repeat_fold_0_or_more(
-        delimited(
-            Token::LBracket,
-            u32.verify(|dim| *dim != 0),
-            Token::RBracket,
-        ),
+        delimited(Token::LBracket, array_dimension, Token::RBracket),
         move || result,
         |mut result: Vec<u32>, x| {
             result.push(x);
@@ -867,10 +1541,21 @@ fn array_dimensions<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<V
     )
 }
 
-// Copied and fixed from Winnow sources (fold_repeat0_)
-// Winnow Repeat::fold takes FnMut() -> Result to initalize accumulator,
-// this really should be FnOnce() -> Result
-fn repeat_fold_0_or_more<I, O, E, F, G, H, R>(
+fn array_dimension<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<u32> {
+    take_error(u32.map(|dim| {
+        if dim == 0 {
+            Err((1, PtxError::ZeroDimensionArray))
+        } else {
+            Ok(dim)
+        }
+    }))
+    .parse_next(stream)
+}
+
+// Copied and fixed from Winnow sources (fold_repeat0_)
+// Winnow Repeat::fold takes FnMut() -> Result to initalize accumulator,
+// this really should be FnOnce() -> Result
+fn repeat_fold_0_or_more<I, O, E, F, G, H, R>(
     mut f: F,
     init: H,
     mut g: G,
@@ -967,10 +1652,235 @@ fn scalar_type<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<Scalar
 
 fn predicated_instruction<'a, 'input>(
     stream: &mut PtxParser<'a, 'input>,
-) -> PResult<ast::Statement<ParsedOperandStr<'input>>> {
-    (opt(pred_at), parse_instruction, Token::Semicolon)
+) -> PResult<Option<ast::Statement<ParsedOperandStr<'input>>>> {
+    let errors_before = stream.state.errors.len();
+    let statement = (opt(pred_at), parse_instruction, Token::Semicolon)
         .map(|(p, i, _)| ast::Statement::Instruction(p, i))
-        .parse_next(stream)
+        .parse_next(stream)?;
+    // Guards can't be forward references, the predicate must already be declared
+    if let ast::Statement::Instruction(Some(ref guard), _) = statement {
+        let declared = matches!(
+            stream.state.variable_type(guard.label),
+            Some((ast::Type::Scalar(ScalarType::Pred), _))
+        );
+        if !declared {
+            stream.state.errors.push(PtxError::SyntaxError);
+        }
+    }
+    if let ast::Statement::Instruction(_, ref instruction) = statement {
+        check_no_predicate_operands(&mut stream.state, instruction);
+        check_vector_elements(&mut stream.state, instruction);
+        check_address_register(&mut stream.state, instruction);
+        check_immediate_ranges(&mut stream.state, instruction);
+        let writes_const = match instruction {
+            ast::Instruction::St { data, .. } => data.state_space == StateSpace::Const,
+            ast::Instruction::Atom { data, .. } => data.space == StateSpace::Const,
+            ast::Instruction::AtomCas { data, .. } => data.space == StateSpace::Const,
+            _ => false,
+        };
+        if writes_const {
+            stream.state.errors.push(PtxError::SyntaxError);
+        }
+    }
+    if let (Some(target), ast::Statement::Instruction(_, ref instruction)) =
+        (stream.state.options.target_arch, &statement)
+    {
+        let required = instruction.min_arch();
+        if required > target {
+            stream
+                .state
+                .errors
+                .push(PtxError::UnsupportedArch { required, target });
+        }
+    }
+    let unsupported = stream.state.errors[errors_before..]
+        .iter()
+        .any(|err| matches!(err, PtxError::Todo));
+    if unsupported && stream.state.options.strict_unsupported {
+        Ok(None)
+    } else {
+        Ok(Some(statement))
+    }
+}
+
+// Predicates have no numeric value, they can only be written by comparisons
+// and read by guards, `selp` and the logic instructions
+fn check_no_predicate_operands(
+    state: &mut PtxParserState,
+    instruction: &ast::Instruction<ParsedOperandStr>,
+) {
+    if !matches!(
+        instruction,
+        ast::Instruction::Add { .. }
+            | ast::Instruction::Sub { .. }
+            | ast::Instruction::Mul { .. }
+            | ast::Instruction::Mad { .. }
+            | ast::Instruction::Ld { .. }
+            | ast::Instruction::St { .. }
+    ) {
+        return;
+    }
+    let mut has_predicate = false;
+    let _ = ast::visit(
+        instruction,
+        &mut |operand: &ParsedOperandStr,
+              _: Option<(&ast::Type, StateSpace)>,
+              _: bool,
+              _: bool| {
+            let names = match operand {
+                ast::ParsedOperand::Reg(name)
+                | ast::ParsedOperand::RegOffset(name, _)
+                | ast::ParsedOperand::VecMember(name, _) => std::slice::from_ref(name),
+                ast::ParsedOperand::VecPack(names) => &names[..],
+                ast::ParsedOperand::Imm(_)
+                | ast::ParsedOperand::SymbolOffset(..)
+                | ast::ParsedOperand::SpecialRegisterDimension(..)
+                | ast::ParsedOperand::Generic(_) => &[],
+            };
+            has_predicate |= names.iter().any(|name| {
+                matches!(
+                    state.variable_type(name),
+                    Some((ast::Type::Scalar(ScalarType::Pred), _))
+                )
+            });
+            Ok::<_, std::convert::Infallible>(())
+        },
+    );
+    if has_predicate {
+        state.errors.push(PtxError::SyntaxError);
+    }
+}
+
+// A vector ld/st names one register per element, `.v2.bf16x2` takes two .b32
+// registers. Registers of `{a, b, c, d}` must match the element type.
+// As with the other memory operands, any type of the same size is accepted,
+// except for mixing integers with floats. Integer registers can also be wider
+// than the element, values are then extended or truncated
+fn check_vector_elements(
+    state: &mut PtxParserState,
+    instruction: &ast::Instruction<ParsedOperandStr>,
+) {
+    let (type_, operand) = match instruction {
+        ast::Instruction::Ld { data, arguments } => (&data.typ, &arguments.dst),
+        ast::Instruction::St { data, arguments } => (&data.typ, &arguments.src2),
+        _ => return,
+    };
+    let (len, element, names) = match (type_, operand) {
+        (ast::Type::Vector(len, element), ast::ParsedOperand::VecPack(names)) => {
+            (*len, *element, names)
+        }
+        _ => return,
+    };
+    if names.len() != len as usize {
+        state.errors.push(PtxError::SyntaxError);
+        return;
+    }
+    let is_float = |type_: ScalarType| type_.kind() == ast::ScalarKind::Float;
+    let matches = |declared: ScalarType| {
+        if is_float(declared) || is_float(element) {
+            declared.size_of() == element.size_of()
+                && (declared.kind() == ast::ScalarKind::Bit
+                    || element.kind() == ast::ScalarKind::Bit
+                    || is_float(declared) == is_float(element))
+        } else {
+            declared != ScalarType::Pred && declared.size_of() >= element.size_of()
+        }
+    };
+    let mismatched = names.iter().any(|name| match state.variable_type(name) {
+        Some((ast::Type::Scalar(declared), StateSpace::Reg)) => !matches(*declared),
+        Some(_) => true,
+        None => false,
+    });
+    if mismatched {
+        state.errors.push(PtxError::SyntaxError);
+    }
+}
+
+// Global and generic addresses are 64 bits wide, so a register holding one
+// must be too. Shared, local and parameter addresses are window
+// offsets, they can be held in 32-bit registers even in 64-bit modules
+fn check_address_register(
+    state: &mut PtxParserState,
+    instruction: &ast::Instruction<ParsedOperandStr>,
+) {
+    let (space, address) = match instruction {
+        ast::Instruction::Ld { data, arguments } => (data.state_space, &arguments.src),
+        ast::Instruction::St { data, arguments } => (data.state_space, &arguments.src1),
+        ast::Instruction::Atom { data, arguments } => (data.space, &arguments.src1),
+        ast::Instruction::AtomCas { data, arguments } => (data.space, &arguments.src1),
+        _ => return,
+    };
+    if space != StateSpace::Global && space != StateSpace::Generic {
+        return;
+    }
+    let base = match address {
+        ast::ParsedOperand::Reg(name) | ast::ParsedOperand::RegOffset(name, _) => name,
+        _ => return,
+    };
+    if let Some((type_, StateSpace::Reg)) = state.variable_type(base) {
+        if type_size(type_) != 8 {
+            state.errors.push(PtxError::SyntaxError);
+        }
+    }
+}
+
+// Integer immediates of arithmetic instructions must fit in the operand type.
+// Both signed and unsigned interpretations are accepted, so `add.u16 d, a, -1`
+// and `add.s16 d, a, 0xffff` are fine
+fn check_immediate_ranges(
+    state: &mut PtxParserState,
+    instruction: &ast::Instruction<ParsedOperandStr>,
+) {
+    if !matches!(
+        instruction,
+        ast::Instruction::Add { .. }
+            | ast::Instruction::Sub { .. }
+            | ast::Instruction::Mul { .. }
+            | ast::Instruction::Mad { .. }
+            | ast::Instruction::Min { .. }
+            | ast::Instruction::Max { .. }
+    ) {
+        return;
+    }
+    let _ = ast::visit(
+        instruction,
+        &mut |operand: &ParsedOperandStr,
+              type_space: Option<(&ast::Type, StateSpace)>,
+              _: bool,
+              _: bool| {
+            let type_ = match type_space {
+                Some((ast::Type::Scalar(type_), _)) => *type_,
+                _ => return Ok(()),
+            };
+            let expected_type = match type_ {
+                ScalarType::U8 => "u8",
+                ScalarType::U16 => "u16",
+                ScalarType::U32 => "u32",
+                ScalarType::S8 => "s8",
+                ScalarType::S16 => "s16",
+                ScalarType::S32 => "s32",
+                _ => return Ok(()),
+            };
+            let bits = type_.size_of() as u32 * 8;
+            let (value, fits) = match operand {
+                ast::ParsedOperand::Imm(ast::ImmediateValue::U64(value)) => {
+                    (value.to_string(), *value < 1u64 << bits)
+                }
+                ast::ParsedOperand::Imm(ast::ImmediateValue::S64(value)) => (
+                    value.to_string(),
+                    (-(1i64 << (bits - 1))..1i64 << bits).contains(value),
+                ),
+                _ => return Ok(()),
+            };
+            if !fits {
+                state.errors.push(PtxError::LiteralOutOfRange {
+                    value,
+                    expected_type,
+                });
+            }
+            Ok::<_, std::convert::Infallible>(())
+        },
+    );
 }
 
 fn pred_at<'a, 'input>(stream: &mut PtxParser<'a, 'input>) -> PResult<ast::PredAt<&'input str>> {
@@ -1045,16 +1955,38 @@ fn ident_literal<
     }
 }
 
-fn u8_literal<'a, 'input>(x: u8) -> impl Parser<PtxParser<'a, 'input>, (), ContextError> {
-    move |stream: &mut PtxParser| u8.verify(|t| *t == x).void().parse_next(stream)
-}
+// Special registers whose `.x`, `.y` and `.z` suffixes select a dimension
+const DIMENSIONED_SPECIAL_REGISTERS: [&str; 8] = [
+    "%tid",
+    "%ntid",
+    "%ctaid",
+    "%nctaid",
+    "%clusterid",
+    "%nclusterid",
+    "%cluster_ctaid",
+    "%cluster_nctaid",
+];
 
 impl<Ident> ast::ParsedOperand<Ident> {
     fn parse<'a, 'input>(
         stream: &mut PtxParser<'a, 'input>,
+    ) -> PResult<ast::ParsedOperand<&'input str>> {
+        let operand = Self::parse_address(stream)?;
+        // `myArr+16` outside of brackets is the address of a variable or
+        // function plus an offset
+        Ok(match operand {
+            ast::ParsedOperand::RegOffset(name, offset) if stream.state.is_symbol(name) => {
+                ast::ParsedOperand::SymbolOffset(name, offset)
+            }
+            operand => operand,
+        })
+    }
+
+    // Operand between brackets, where `a+8` is always a base and an offset
+    fn parse_address<'a, 'input>(
+        stream: &mut PtxParser<'a, 'input>,
     ) -> PResult<ast::ParsedOperand<&'input str>> {
         use winnow::combinator::*;
-        use winnow::token::any;
         fn vector_index<'input>(inp: &'input str) -> Result<u8, PtxError> {
             match inp {
                 ".x" | ".r" => Ok(0),
@@ -1064,6 +1996,15 @@ impl<Ident> ast::ParsedOperand<Ident> {
                 _ => Err(PtxError::WrongVectorElement),
             }
         }
+        // Special registers with a `.x`, `.y` and `.z` component per dimension
+        fn dimension_index<'input>(inp: &'input str) -> Result<u8, PtxError> {
+            match inp {
+                ".x" => Ok(0),
+                ".y" => Ok(1),
+                ".z" => Ok(2),
+                _ => Err(PtxError::WrongVectorElement),
+            }
+        }
         fn ident_operands<'a, 'input>(
             stream: &mut PtxParser<'a, 'input>,
         ) -> PResult<ast::ParsedOperand<&'input str>> {
@@ -1072,6 +2013,14 @@ impl<Ident> ast::ParsedOperand<Ident> {
                 preceded(Token::Plus, s32)
                     .map(move |offset| ast::ParsedOperand::RegOffset(main_ident, offset)),
                 take_error(dot_ident.map(move |suffix| {
+                    if DIMENSIONED_SPECIAL_REGISTERS.contains(&main_ident) {
+                        let dimension = dimension_index(suffix).map_err(move |e| {
+                            (ast::ParsedOperand::SpecialRegisterDimension(main_ident, 0), e)
+                        })?;
+                        return Ok(ast::ParsedOperand::SpecialRegisterDimension(
+                            main_ident, dimension,
+                        ));
+                    }
                     let vector_index = vector_index(suffix)
                         .map_err(move |e| (ast::ParsedOperand::VecMember(main_ident, 0), e))?;
                     Ok(ast::ParsedOperand::VecMember(main_ident, vector_index))
@@ -1083,16 +2032,29 @@ impl<Ident> ast::ParsedOperand<Ident> {
         fn vector_operand<'a, 'input>(
             stream: &mut PtxParser<'a, 'input>,
         ) -> PResult<Vec<&'input str>> {
-            let (_, r1, _, r2) = (Token::LBrace, ident, Token::Comma, ident).parse_next(stream)?;
-            // TODO: parse .v8 literals
-            dispatch! {any;
-                Token::RBrace => empty.map(|_| vec![r1, r2]),
-                Token::Comma => (ident, Token::Comma, ident, Token::RBrace).map(|(r3, _, r4, _)| vec![r1, r2, r3, r4]),
-                _ => fail
-            }
+            // Register groups match the vector widths: .v2, .v4 and .v8
+            delimited(
+                Token::LBrace,
+                separated(2..=8, ident, Token::Comma),
+                Token::RBrace,
+            )
+            .verify(|elements: &Vec<&'input str>| elements.len().is_power_of_two())
             .parse_next(stream)
         }
+        fn generic_operand<'a, 'input>(
+            stream: &mut PtxParser<'a, 'input>,
+        ) -> PResult<&'input str> {
+            let (_, _, name, _) = (
+                ident.verify(|name: &str| name == "generic"),
+                Token::LParen,
+                ident,
+                Token::RParen,
+            )
+                .parse_next(stream)?;
+            Ok(name)
+        }
         alt((
+            generic_operand.map(ast::ParsedOperand::Generic),
             ident_operands,
             immediate_value.map(ast::ParsedOperand::Imm),
             vector_operand.map(ast::ParsedOperand::VecPack),
@@ -1141,6 +2103,8 @@ pub enum PtxError {
     #[error("")]
     MultiArrayVariable,
     #[error("")]
+    RegisterArray,
+    #[error("")]
     ZeroDimensionArray,
     #[error("")]
     ArrayInitalizer,
@@ -1150,6 +2114,47 @@ pub enum PtxError {
     UnrecognizedStatement { start: usize, end: usize },
     #[error("{start}:{end}")]
     UnrecognizedDirective { start: usize, end: usize },
+    #[error("")]
+    UnexpectedParamAccess,
+    #[error("")]
+    MisalignedAccess,
+    #[error("")]
+    MovTypePun,
+    #[error("")]
+    DivergentUniformBranch,
+    #[error("")]
+    DivergentBarrier,
+    #[error("unsupported PTX version {major}.{minor}")]
+    UnsupportedVersion { major: u8, minor: u8 },
+    #[error("instruction requires sm_{required}, target is sm_{target}")]
+    UnsupportedArch { required: u32, target: u32 },
+    #[error("shfl.sync clamp value {value:#x} sets bits outside of the lane and segment masks")]
+    ShuffleClampOutOfRange { value: u64 },
+    #[error("{name} is defined more than once")]
+    DuplicateDefinition { name: String },
+    #[error("declaration of {name} does not match its definition")]
+    SignatureMismatch { name: String },
+    #[error("literal {value} does not fit in {expected_type}")]
+    LiteralOutOfRange {
+        value: String,
+        expected_type: &'static str,
+    },
+}
+
+impl PtxError {
+    // Warnings do not stop the module from being returned to the caller
+    pub fn is_warning(&self) -> bool {
+        matches!(
+            self,
+            PtxError::UnexpectedParamAccess
+                | PtxError::MisalignedAccess
+                | PtxError::MovTypePun
+                | PtxError::DivergentUniformBranch
+                | PtxError::DivergentBarrier
+                | PtxError::UnsupportedVersion { .. }
+                | PtxError::ShuffleClampOutOfRange { .. }
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -1255,16 +2260,53 @@ impl<'input, I: Stream<Token = Self> + StreamIsPartial, E: ParserError<I>> Parse
 fn bra<'a, 'input>(
     stream: &mut PtxParser<'a, 'input>,
 ) -> PResult<ast::Instruction<ParsedOperandStr<'input>>> {
-    preceded(
-        opt(Token::DotUni),
-        any.verify_map(|t| match t {
-            Token::Ident(ident) => Some(ast::Instruction::Bra {
-                arguments: BraArgs { src: ident },
-            }),
+    (opt(Token::DotUni), ident)
+        .map(|(uni, ident)| ast::Instruction::Bra {
+            data: ast::BraData {
+                uniform: uni.is_some(),
+            },
+            arguments: BraArgs { src: ident },
+        })
+        .parse_next(stream)
+}
+
+// `.l` and `.r` are not tokens of their own, they would clash with vector
+// members like `v.r`
+fn shf<'a, 'input>(
+    stream: &mut PtxParser<'a, 'input>,
+) -> PResult<ast::Instruction<ParsedOperandStr<'input>>> {
+    let direction = dot_ident
+        .verify_map(|suffix| match suffix {
+            ".l" => Some(ast::ShiftDirection::Left),
+            ".r" => Some(ast::ShiftDirection::Right),
             _ => None,
-        }),
+        })
+        .parse_next(stream)?;
+    let mode = alt((
+        Token::DotClamp.value(ast::VideoShiftMode::Clamp),
+        Token::DotWrap.value(ast::VideoShiftMode::Wrap),
+    ))
+    .parse_next(stream)?;
+    let (_, d, _, a, _, b, _, c) = (
+        Token::DotB32,
+        ParsedOperand::<&'input str>::parse,
+        Token::Comma,
+        ParsedOperand::<&'input str>::parse,
+        Token::Comma,
+        ParsedOperand::<&'input str>::parse,
+        Token::Comma,
+        ParsedOperand::<&'input str>::parse,
     )
-    .parse_next(stream)
+        .parse_next(stream)?;
+    Ok(ast::Instruction::Shf {
+        data: ast::ShfDetails { direction, mode },
+        arguments: ShfArgs {
+            dst: d,
+            src1: a,
+            src2: b,
+            src3: c,
+        },
+    })
 }
 
 fn call<'a, 'input>(
@@ -1304,6 +2346,15 @@ fn call<'a, 'input>(
         stream.state.errors.push(PtxError::MalformedCall);
         return Ok(empty_call(uniform, name));
     }
+    let types_match = return_arguments
+        .iter()
+        .map(|name| ParsedOperand::Reg(*name))
+        .zip(recorded_fn.0.iter())
+        .chain(input_arguments.iter().cloned().zip(recorded_fn.1.iter()))
+        .all(|(operand, (type_, _))| call_argument_matches(&stream.state, &operand, type_));
+    if !types_match {
+        stream.state.errors.push(PtxError::SyntaxError);
+    }
     let data = CallDetails {
         uniform,
         return_arguments: recorded_fn.0.clone(),
@@ -1317,6 +2368,31 @@ fn call<'a, 'input>(
     Ok(ast::Instruction::Call { data, arguments })
 }
 
+// Like instruction operands, scalar arguments only have to match in size:
+// a .u64 register can be passed to a .b64 parameter
+fn call_argument_matches(
+    state: &PtxParserState,
+    operand: &ParsedOperandStr,
+    type_: &ast::Type,
+) -> bool {
+    if state.operand_has_type(operand, type_) {
+        return true;
+    }
+    match (operand, type_) {
+        (ast::ParsedOperand::Reg(name), ast::Type::Scalar(scalar))
+            if *scalar != ScalarType::Pred =>
+        {
+            match state.variable_type(name) {
+                Some((ast::Type::Scalar(var_type), _)) => {
+                    *var_type != ScalarType::Pred && var_type.size_of() == scalar.size_of()
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
 fn empty_call<'input>(
     uniform: bool,
     name: &'input str,
@@ -1335,6 +2411,155 @@ fn empty_call<'input>(
     }
 }
 
+// Shared memory is only visible to threads of a single cluster, so ordering
+// its accesses at the system scope makes no sense
+fn scope_allows_space(scope: MemScope, space: Option<StateSpace>) -> bool {
+    match space {
+        Some(StateSpace::Shared | StateSpace::SharedCta | StateSpace::SharedCluster) => {
+            scope != MemScope::Sys
+        }
+        _ => true,
+    }
+}
+
+// Immediate bit positions must index a bit of the operated type and field
+// lengths must fit in it, values in registers are clamped by the hardware and
+// left unchecked
+fn bit_field_operands_in_range(
+    type_: ScalarType,
+    position: &ParsedOperandStr,
+    length: &ParsedOperandStr,
+) -> bool {
+    let bits = type_.size_of() as i64 * 8;
+    let in_range = |operand: &ParsedOperandStr, max: i64| match operand {
+        ast::ParsedOperand::Imm(ImmediateValue::U64(value)) => *value <= max as u64,
+        ast::ParsedOperand::Imm(ImmediateValue::S64(value)) => (0..=max).contains(value),
+        ast::ParsedOperand::Imm(_) => false,
+        _ => true,
+    };
+    in_range(position, bits - 1) && in_range(length, bits)
+}
+
+// Float and vector atomics do not allow type-relaxed operands, check
+// destination and value registers against the declared types. Bit-size types,
+// including the half precision .f16, .bf16 and packed .f16x2, .bf16x2 types,
+// can be held in any register of the same width, e.g. `.reg .b16 %h` for an
+// .f16 value. Identifiers missing from the symbol table are not checked here
+fn check_atom_operands(
+    state: &mut PtxParserState,
+    type_: &ast::Type,
+    dst: &ParsedOperandStr,
+    src: &ParsedOperandStr,
+) {
+    let (len, scalar) = match type_ {
+        ast::Type::Scalar(scalar) => (None, *scalar),
+        ast::Type::Vector(len, scalar) => (Some(*len as usize), *scalar),
+        _ => return,
+    };
+    let is_bit_size = scalar.kind() == ast::ScalarKind::Bit
+        || matches!(
+            scalar,
+            ScalarType::F16 | ScalarType::BF16 | ScalarType::F16x2 | ScalarType::BF16x2
+        );
+    let has_width = |name: &str| match state.variable_type(name) {
+        Some((ast::Type::Scalar(var_type), _)) => {
+            *var_type != ScalarType::Pred && var_type.size_of() == scalar.size_of()
+        }
+        Some(_) => false,
+        None => true,
+    };
+    let has_type = |operand: &ParsedOperandStr| {
+        if state.operand_has_type(operand, type_) {
+            return true;
+        }
+        match (operand, len) {
+            _ if !is_bit_size => false,
+            (ast::ParsedOperand::Reg(name), None) => has_width(name),
+            (ast::ParsedOperand::VecPack(names), Some(len)) => {
+                names.len() == len && names.iter().all(|name| has_width(name))
+            }
+            _ => false,
+        }
+    };
+    if !has_type(dst) || !has_type(src) {
+        state.errors.push(PtxError::SyntaxError);
+    }
+}
+
+// Integer operands can be held in registers of any type of the same width,
+// e.g. `mul.hi.u64` returns the high half of the product in a 64-bit
+// register. Identifiers missing from the symbol table are not checked here
+fn check_operand_width(state: &mut PtxParserState, operand: &ParsedOperandStr, type_: ScalarType) {
+    let width = match operand {
+        ast::ParsedOperand::Reg(name) => match state.variable_type(name) {
+            Some((var_type, StateSpace::Reg)) => type_size(var_type),
+            _ => return,
+        },
+        _ => return,
+    };
+    if width != type_.size_of() as u32 {
+        state.errors.push(PtxError::SyntaxError);
+    }
+}
+
+// Compare and swap operands must be as wide as the swapped value. A 128-bit
+// value is held either in a .b128 register or in a `{lo, hi}` pair of 64-bit
+// registers
+fn check_cas_operand(state: &mut PtxParserState, operand: &ParsedOperandStr, type_: ScalarType) {
+    match operand {
+        ast::ParsedOperand::VecPack(names) => {
+            let is_half = |name: &&str| match state.variable_type(name) {
+                Some((var_type, StateSpace::Reg)) => type_size(var_type) == 8,
+                Some(_) => false,
+                None => true,
+            };
+            if type_ != ScalarType::B128 || names.len() != 2 || !names.iter().all(is_half) {
+                state.errors.push(PtxError::SyntaxError);
+            }
+        }
+        ast::ParsedOperand::Imm(_) if type_ == ScalarType::B128 => {
+            state.errors.push(PtxError::SyntaxError);
+        }
+        _ => check_operand_width(state, operand, type_),
+    }
+}
+
+// Copies between integer and floating point registers are only meant to be
+// done with the `.b*` types. Other combinations are accepted, but since they
+// are not a bit reinterpretation, they most likely hide a bug
+fn check_mov_type_class(state: &mut PtxParserState, operand: &ParsedOperandStr, type_: ScalarType) {
+    let declared = match operand {
+        ast::ParsedOperand::Reg(name) => match state.variable_type(name) {
+            Some((ast::Type::Scalar(declared), StateSpace::Reg)) => *declared,
+            _ => return,
+        },
+        _ => return,
+    };
+    let is_float = |kind| kind == ast::ScalarKind::Float;
+    let (declared_kind, kind) = (declared.kind(), type_.kind());
+    if declared_kind == ast::ScalarKind::Bit || kind == ast::ScalarKind::Bit {
+        return;
+    }
+    if is_float(declared_kind) != is_float(kind) {
+        state.errors.push(PtxError::MovTypePun);
+    }
+}
+
+// The `c` operand of shfl.sync packs the clamp lane in bits 0-4 and the
+// segment mask in bits 8-12. Other bits are ignored by the hardware, so an
+// immediate setting them is most likely a mistake
+fn check_shfl_clamp(state: &mut PtxParserState, c: &ParsedOperandStr) {
+    const VALID_BITS: u64 = 0x1f1f;
+    let value = match c {
+        ast::ParsedOperand::Imm(ast::ImmediateValue::U64(value)) => *value,
+        ast::ParsedOperand::Imm(ast::ImmediateValue::S64(value)) => *value as u64,
+        _ => return,
+    };
+    if value & !VALID_BITS != 0 {
+        state.errors.push(PtxError::ShuffleClampOutOfRange { value });
+    }
+}
+
 type ParsedOperandStr<'input> = ast::ParsedOperand<&'input str>;
 
 #[derive(Clone, PartialEq, Default, Debug, Display)]
@@ -1408,7 +2633,7 @@ derive_parser!(
     #[derive(Logos, PartialEq, Eq, Debug, Clone, Copy)]
     #[logos(skip r"(?:\s+)|(?://[^\n\r]*[\n\r]*)|(?:/\*[^*]*\*+(?:[^/*][^*]*\*+)*/)")]
     #[logos(error = TokenError)]
-    enum Token<'input> {
+    pub enum Token<'input> {
         #[token(",")]
         Comma,
         #[token(".")]
@@ -1419,12 +2644,14 @@ derive_parser!(
         Semicolon,
         #[token("@")]
         At,
+        #[regex(r"@@DWARF[^\n\r]*", |lex| lex.slice())]
+        DwarfLine(&'input str),
         #[regex(r"[a-zA-Z][a-zA-Z0-9_$]*|[_$%][a-zA-Z0-9_$]+", |lex| lex.slice(), priority = 0)]
         Ident(&'input str),
         #[regex(r"\.[a-zA-Z][a-zA-Z0-9_$]*|\.[_$%][a-zA-Z0-9_$]+", |lex| lex.slice(), priority = 0)]
         DotIdent(&'input str),
-        #[regex(r#""[^"]*""#)]
-        String,
+        #[regex(r#""[^"]*""#, |lex| lex.slice())]
+        String(&'input str),
         #[token("|")]
         Pipe,
         #[token("!")]
@@ -1516,8 +2743,21 @@ derive_parser!(
 
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#data-movement-and-conversion-instructions-mov
     mov{.vec}.type  d, a => {
+        if vec.is_none() {
+            check_operand_width(state, &d, type_);
+            check_operand_width(state, &a, type_);
+            check_mov_type_class(state, &d, type_);
+            check_mov_type_class(state, &a, type_);
+        }
+        let mut data = ast::MovDetails::new(vec, type_);
+        // `mov.u64 r, foo;` takes the address of function `foo`, unless
+        // there's a variable shadowing it
+        if let ParsedOperand::Reg(name) = a {
+            data.src_is_address = state.variable(name).is_none()
+                && state.function_declarations.contains_key(name);
+        }
         Instruction::Mov {
-            data: ast::MovDetails::new(vec, type_),
+            data,
             arguments: MovArgs { dst: d, src: a },
         }
     }
@@ -1536,6 +2776,7 @@ derive_parser!(
         Instruction::St {
             data: StData {
                 qualifier: weak.unwrap_or(RawLdStQualifier::Weak).into(),
+                weak_explicit: weak.is_some(),
                 state_space: ss.unwrap_or(StateSpace::Generic),
                 caching: cop.unwrap_or(RawStCacheOperator::Wb).into(),
                 typ: ast::Type::maybe_vector(vec, type_)
@@ -1547,6 +2788,7 @@ derive_parser!(
         Instruction::St {
             data: StData {
                 qualifier: volatile.into(),
+                weak_explicit: false,
                 state_space: ss.unwrap_or(StateSpace::Generic),
                 caching: ast::StCacheOperator::Writeback,
                 typ: ast::Type::maybe_vector(vec, type_)
@@ -1558,9 +2800,13 @@ derive_parser!(
         if level_eviction_priority.is_some() || level_cache_hint || cache_policy.is_some() {
             state.errors.push(PtxError::Todo);
         }
+        if !scope_allows_space(scope, ss) {
+            state.errors.push(PtxError::SyntaxError);
+        }
         Instruction::St {
             data: StData {
                 qualifier: ast::LdStQualifier::Relaxed(scope),
+                weak_explicit: false,
                 state_space: ss.unwrap_or(StateSpace::Generic),
                 caching: ast::StCacheOperator::Writeback,
                 typ: ast::Type::maybe_vector(vec, type_)
@@ -1572,9 +2818,13 @@ derive_parser!(
         if level_eviction_priority.is_some() || level_cache_hint || cache_policy.is_some() {
             state.errors.push(PtxError::Todo);
         }
+        if !scope_allows_space(scope, ss) {
+            state.errors.push(PtxError::SyntaxError);
+        }
         Instruction::St {
             data: StData {
                 qualifier: ast::LdStQualifier::Release(scope),
+                weak_explicit: false,
                 state_space: ss.unwrap_or(StateSpace::Generic),
                 caching: ast::StCacheOperator::Writeback,
                 typ: ast::Type::maybe_vector(vec, type_)
@@ -1587,6 +2837,7 @@ derive_parser!(
         Instruction::St {
             data: ast::StData {
                 qualifier: ast::LdStQualifier::Relaxed(MemScope::Sys),
+                weak_explicit: false,
                 state_space: global.unwrap_or(StateSpace::Generic),
                 caching: ast::StCacheOperator::Writeback,
                 typ: type_.into()
@@ -1594,17 +2845,19 @@ derive_parser!(
             arguments: ast::StArgs { src1:a, src2:b }
         }
     }
-    .ss: StateSpace =           { .global, .local, .param{::func}, .shared{::cta, ::cluster} };
+    // .const is read-only, it's only accepted to report a better error
+    .ss: StateSpace =           { .const, .global, .local, .param{::func}, .shared{::cta, ::cluster} };
     .level::eviction_priority: EvictionPriority =
                                 { .L1::evict_normal, .L1::evict_unchanged, .L1::evict_first, .L1::evict_last, .L1::no_allocate };
     .level::cache_hint =        { .L2::cache_hint };
     .cop: RawStCacheOperator =  { .wb, .cg, .cs, .wt };
     .scope: MemScope =          { .cta, .cluster, .gpu, .sys };
-    .vec: VectorPrefix =        { .v2, .v4 };
+    .vec: VectorPrefix =        { .v2, .v4, .v8 };
     .type: ScalarType =         { .b8, .b16, .b32, .b64, .b128,
                                   .u8, .u16, .u32, .u64,
                                   .s8, .s16, .s32, .s64,
-                                  .f32, .f64 };
+                                  .f32, .f64,
+                                  .f16x2, .bf16x2 };
     RawLdStQualifier =          { .weak, .volatile };
     StateSpace =                { .global };
 
@@ -1617,6 +2870,7 @@ derive_parser!(
         Instruction::Ld {
             data: LdDetails {
                 qualifier: weak.unwrap_or(RawLdStQualifier::Weak).into(),
+                weak_explicit: weak.is_some(),
                 state_space: ss.unwrap_or(StateSpace::Generic),
                 caching: cop.unwrap_or(RawLdCacheOperator::Ca).into(),
                 typ: ast::Type::maybe_vector(vec, type_),
@@ -1632,6 +2886,7 @@ derive_parser!(
         Instruction::Ld {
             data: LdDetails {
                 qualifier: volatile.into(),
+                weak_explicit: false,
                 state_space: ss.unwrap_or(StateSpace::Generic),
                 caching: ast::LdCacheOperator::Cached,
                 typ: ast::Type::maybe_vector(vec, type_),
@@ -1644,9 +2899,13 @@ derive_parser!(
         if level_eviction_priority.is_some() || level_cache_hint || level_prefetch_size.is_some() || cache_policy.is_some() {
             state.errors.push(PtxError::Todo);
         }
+        if !scope_allows_space(scope, ss) {
+            state.errors.push(PtxError::SyntaxError);
+        }
         Instruction::Ld {
             data: LdDetails {
                 qualifier: ast::LdStQualifier::Relaxed(scope),
+                weak_explicit: false,
                 state_space: ss.unwrap_or(StateSpace::Generic),
                 caching: ast::LdCacheOperator::Cached,
                 typ: ast::Type::maybe_vector(vec, type_),
@@ -1659,9 +2918,13 @@ derive_parser!(
         if level_eviction_priority.is_some() || level_cache_hint || level_prefetch_size.is_some() || cache_policy.is_some() {
             state.errors.push(PtxError::Todo);
         }
+        if !scope_allows_space(scope, ss) {
+            state.errors.push(PtxError::SyntaxError);
+        }
         Instruction::Ld {
             data: LdDetails {
                 qualifier: ast::LdStQualifier::Acquire(scope),
+                weak_explicit: false,
                 state_space: ss.unwrap_or(StateSpace::Generic),
                 caching: ast::LdCacheOperator::Cached,
                 typ: ast::Type::maybe_vector(vec, type_),
@@ -1675,6 +2938,7 @@ derive_parser!(
         Instruction::Ld {
             data: LdDetails {
                 qualifier: ast::LdStQualifier::Relaxed(MemScope::Sys),
+                weak_explicit: false,
                 state_space: global.unwrap_or(StateSpace::Generic),
                 caching: ast::LdCacheOperator::Cached,
                 typ: type_.into(),
@@ -1690,26 +2954,33 @@ derive_parser!(
     .level::cache_hint =                    { .L2::cache_hint };
     .level::prefetch_size: PrefetchSize =   { .L2::64B, .L2::128B, .L2::256B };
     .scope: MemScope =                      { .cta, .cluster, .gpu, .sys };
-    .vec: VectorPrefix =                    { .v2, .v4 };
+    .vec: VectorPrefix =                    { .v2, .v4, .v8 };
     .type: ScalarType =                     { .b8, .b16, .b32, .b64, .b128,
                                               .u8, .u16, .u32, .u64,
                                               .s8, .s16, .s32, .s64,
-                                              .f32, .f64 };
+                                              .f32, .f64,
+                                              .f16x2, .bf16x2 };
     RawLdStQualifier =                      { .weak, .volatile };
     StateSpace =                            { .global };
 
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#data-movement-and-conversion-instructions-ld-global-nc
-    ld.global{.cop}.nc{.level::eviction_priority}{.level::cache_hint}{.level::prefetch_size}{.vec}.type  d, [a]{, cache_policy} => {
+    ld{.ss}{.cop}.nc{.level::eviction_priority}{.level::cache_hint}{.level::prefetch_size}{.vec}.type     d, [a]{, cache_policy} => {
         if cop.is_some() && level_eviction_priority.is_some() {
             state.errors.push(PtxError::SyntaxError);
         }
+        // Non-coherent loads go through the read-only data cache, which is
+        // only available for the global space
+        if ss != Some(StateSpace::Global) || type_ == ScalarType::Pred {
+            state.errors.push(PtxError::SyntaxError);
+        }
         if level_eviction_priority.is_some() || level_cache_hint || level_prefetch_size.is_some() || cache_policy.is_some() {
             state.errors.push(PtxError::Todo);
         }
         Instruction::Ld {
             data: LdDetails {
                 qualifier: ast::LdStQualifier::Weak,
-                state_space: global,
+                weak_explicit: false,
+                state_space: StateSpace::Global,
                 caching: cop.unwrap_or(RawLdCacheOperator::Ca).into(),
                 typ: Type::maybe_vector(vec, type_),
                 non_coherent: true
@@ -1723,15 +2994,20 @@ derive_parser!(
                                               .L1::evict_first, .L1::evict_last, .L1::no_allocate};
     .level::cache_hint =                    { .L2::cache_hint };
     .level::prefetch_size: PrefetchSize =   { .L2::64B, .L2::128B, .L2::256B };
-    .vec: VectorPrefix  =                   { .v2, .v4 };
+    .ss: StateSpace =                       { .const, .global, .local, .param{::entry, ::func}, .shared{::cta, ::cluster} };
+    .vec: VectorPrefix  =                   { .v2, .v4, .v8 };
     .type: ScalarType =                     { .b8, .b16, .b32, .b64, .b128,
                                               .u8, .u16, .u32, .u64,
                                               .s8, .s16, .s32, .s64,
-                                              .f32, .f64 };
-    StateSpace =                { .global };
+                                              .f32, .f64, .f16x2, .bf16x2,
+                                              .pred };
 
     // https://docs.nvidia.com/cuda/parallel-thread-execution/#integer-arithmetic-instructions-add
-    add.type        d, a, b => {
+    add{.rnd}.type  d, a, b => {
+        // Integer addition, including the packed .u16x2/.s16x2 forms, is exact
+        if rnd.is_some() {
+            state.errors.push(PtxError::SyntaxError);
+        }
         Instruction::Add {
             data: ast::ArithDetails::Integer(
                 ast::ArithInteger {
@@ -1757,6 +3033,7 @@ derive_parser!(
             }
         }
     }
+    .rnd: RawRoundingMode = { .rn, .rz, .rm, .rp };
     .type: ScalarType = { .u16, .u32, .u64,
                           .s16, .s64,
                           .u16x2, .s16x2 };
@@ -1864,6 +3141,7 @@ derive_parser!(
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#floating-point-instructions-mul
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#half-precision-floating-point-instructions-mul
     mul.mode.type  d, a, b => {
+        check_operand_width(state, &d, type_);
         ast::Instruction::Mul {
             data: ast::MulDetails::Integer {
                 type_,
@@ -1976,6 +3254,9 @@ derive_parser!(
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#comparison-and-selection-instructions-setp
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#half-precision-comparison-instructions-setp
     setp.CmpOp{.ftz}.type         p[|q], a, b => {
+        if q.as_ref() == Some(&p) {
+            state.errors.push(PtxError::SyntaxError);
+        }
         let data = ast::SetpData::try_parse(state, cmpop, ftz, type_);
         ast::Instruction::Setp {
             data,
@@ -1984,6 +3265,12 @@ derive_parser!(
     }
     setp.CmpOp.BoolOp{.ftz}.type  p[|q], a, b, {!}c => {
         let (negate_src3, c) = c;
+        if q.as_ref() == Some(&p) {
+            state.errors.push(PtxError::SyntaxError);
+        }
+        if !state.operand_has_type(&c, &ast::Type::Scalar(ScalarType::Pred)) {
+            state.errors.push(PtxError::SyntaxError);
+        }
         let base = ast::SetpData::try_parse(state, cmpop, ftz, type_);
         let data = ast::SetpBoolData {
             base,
@@ -2039,31 +3326,76 @@ derive_parser!(
     call <= { call(stream) }
 
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#data-movement-and-conversion-instructions-cvt
-    cvt{.ifrnd}{.ftz}{.sat}.dtype.atype         d, a => {
-        let data = ast::CvtDetails::new(&mut state.errors, ifrnd, ftz, sat, dtype, atype);
+    cvt{.ifrnd}{.ftz}{.sat}{.relu}.dtype.atype  d, a => {
+        let data = ast::CvtDetails::new(state.errors, ifrnd, ftz, sat, relu, dtype, atype);
         let arguments = ast::CvtArgs { dst: d, src: a };
         ast::Instruction::Cvt {
             data, arguments
         }
     }
     // cvt.frnd2{.relu}{.satfinite}.f16.f32       d, a;
-    // cvt.frnd2{.relu}{.satfinite}.f16x2.f32     d, a, b;
     // cvt.frnd2{.relu}{.satfinite}.bf16.f32      d, a;
-    // cvt.frnd2{.relu}{.satfinite}.bf16x2.f32    d, a, b;
-    // cvt.rna{.satfinite}.tf32.f32               d, a;
-    // cvt.frnd2{.relu}.tf32.f32                   d, a;
+    cvt.frnd2{.ftz}{.relu}{.satfinite}.f16x2type.f32    d, a, b => {
+        let _ = f32;
+        ast::Instruction::CvtPack {
+            data: ast::CvtPackDetails {
+                to: f16x2type,
+                rounding: frnd2.into(),
+                flush_to_zero: ftz,
+                relu,
+                saturate_finite: satfinite
+            },
+            arguments: ast::CvtPackArgs { dst: d, src1: a, src2: b }
+        }
+    }
+    cvt.rna{.satfinite}.tf32.f32                d, a => {
+        ast::Instruction::Cvt {
+            data: ast::CvtDetails {
+                from: f32,
+                to: ScalarType::B32,
+                mode: ast::CvtMode::Tf32 {
+                    rounding: ast::Tf32RoundingMode::NearestAway,
+                    relu: false,
+                    saturate_finite: satfinite
+                },
+                saturate: false
+            },
+            arguments: ast::CvtArgs { dst: d, src: a }
+        }
+    }
+    cvt.frnd2{.relu}{.satfinite}.tf32.f32       d, a => {
+        let rounding = match frnd2 {
+            RawRoundingMode::Rz => ast::Tf32RoundingMode::Zero,
+            _ => ast::Tf32RoundingMode::NearestEven,
+        };
+        ast::Instruction::Cvt {
+            data: ast::CvtDetails {
+                from: f32,
+                to: ScalarType::B32,
+                mode: ast::CvtMode::Tf32 {
+                    rounding,
+                    relu,
+                    saturate_finite: satfinite
+                },
+                saturate: false
+            },
+            arguments: ast::CvtArgs { dst: d, src: a }
+        }
+    }
     // cvt.rn.satfinite{.relu}.f8x2type.f32       d, a, b;
     // cvt.rn.satfinite{.relu}.f8x2type.f16x2     d, a;
     // cvt.rn.{.relu}.f16x2.f8x2type              d, a;
 
     .ifrnd: RawRoundingMode =   { .rn,  .rz,  .rm,  .rp,  .rni, .rzi, .rmi, .rpi };
     .frnd2: RawRoundingMode =   { .rn,  .rz };
+    .f16x2type: ScalarType =    { .f16x2, .bf16x2 };
     .dtype: ScalarType =        { .u8,   .u16, .u32, .u64,
                                   .s8,   .s16, .s32, .s64,
                                   .bf16, .f16, .f32, .f64 };
     .atype: ScalarType =        { .u8,   .u16, .u32, .u64,
                                   .s8,   .s16, .s32, .s64,
                                   .bf16, .f16, .f32, .f64 };
+    ScalarType =                { .f32 };
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#logic-and-shift-instructions-shl
     shl.type d, a, b => {
         ast::Instruction::Shl { data: type_, arguments: ShlArgs { dst: d, src1: a, src2: b } }
@@ -2202,11 +3534,14 @@ derive_parser!(
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#integer-arithmetic-instructions-mad
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#floating-point-instructions-mad
     mad.mode.type  d, a, b, c => {
+        check_operand_width(state, &d, type_);
         ast::Instruction::Mad {
             data: ast::MadDetails::Integer {
                 type_,
                 control: mode.into(),
-                saturate: false
+                saturate: false,
+                carry_in: false,
+                carry_out: false
             },
             arguments: MadArgs { dst: d, src1: a, src2: b, src3: c }
         }
@@ -2221,7 +3556,9 @@ derive_parser!(
             data: ast::MadDetails::Integer {
                 type_,
                 control: wide.into(),
-                saturate: false
+                saturate: false,
+                carry_in: false,
+                carry_out: false
             },
             arguments: MadArgs { dst: d, src1: a, src2: b, src3: c  }
         }
@@ -2235,7 +3572,9 @@ derive_parser!(
             data: ast::MadDetails::Integer {
                 type_: s32,
                 control: hi.into(),
-                saturate: true
+                saturate: true,
+                carry_in: false,
+                carry_out: false
             },
             arguments: MadArgs { dst: d, src1: a, src2: b, src3: c  }
         }
@@ -2243,6 +3582,38 @@ derive_parser!(
     RawMulIntControl =  { .hi };
     ScalarType =        { .s32 };
 
+    // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#extended-precision-arithmetic-instructions-mad-cc
+    // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#extended-precision-arithmetic-instructions-madc
+    mad.mode.cc.type  d, a, b, c => {
+        ast::Instruction::Mad {
+            data: ast::MadDetails::Integer {
+                type_,
+                control: mode.into(),
+                saturate: false,
+                carry_in: false,
+                carry_out: true
+            },
+            arguments: MadArgs { dst: d, src1: a, src2: b, src3: c }
+        }
+    }
+    .mode: RawMulIntControl =   { .hi, .lo };
+    .type: ScalarType =         { .u32, .s32, .u64, .s64 };
+
+    madc.mode{.cc}.type  d, a, b, c => {
+        ast::Instruction::Mad {
+            data: ast::MadDetails::Integer {
+                type_,
+                control: mode.into(),
+                saturate: false,
+                carry_in: true,
+                carry_out: cc
+            },
+            arguments: MadArgs { dst: d, src1: a, src2: b, src3: c }
+        }
+    }
+    .mode: RawMulIntControl =   { .hi, .lo };
+    .type: ScalarType =         { .u32, .s32, .u64, .s64 };
+
     mad{.ftz}{.sat}.f32      d, a, b, c => {
         ast::Instruction::Mad {
             data: ast::MadDetails::Float(
@@ -2335,7 +3706,10 @@ derive_parser!(
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#integer-arithmetic-instructions-sub
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#floating-point-instructions-sub
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#half-precision-floating-point-instructions-sub
-    sub.type       d, a, b => {
+    sub{.rnd}.type d, a, b => {
+        if rnd.is_some() {
+            state.errors.push(PtxError::SyntaxError);
+        }
         ast::Instruction::Sub {
             data: ast::ArithDetails::Integer(
                 ArithInteger {
@@ -2357,8 +3731,10 @@ derive_parser!(
             arguments: SubArgs { dst: d, src1: a, src2: b  }
         }
     }
+    .rnd: RawRoundingMode = { .rn, .rz, .rm, .rp };
     .type: ScalarType = { .u16, .u32, .u64,
-                          .s16, .s32, .s64 };
+                          .s16, .s32, .s64,
+                          .u16x2, .s16x2 };
     ScalarType = { .s32 };
 
     sub{.rnd}{.ftz}{.sat}.f32  d, a, b => {
@@ -2772,21 +4148,34 @@ derive_parser!(
     barrier{.cta}.sync{.aligned}    a{, b} => {
         let _ = cta;
         ast::Instruction::Bar {
-            data: ast::BarData { aligned },
+            data: ast::BarData { aligned, spelling: ast::BarSpelling::Barrier },
             arguments: BarArgs { src1: a, src2: b }
         }
     }
-    //barrier{.cta}.arrive{.aligned}    a, b;
+    // Unlike .sync, .arrive requires the thread count
+    barrier{.cta}.arrive{.aligned}  a, b => {
+        let _ = cta;
+        ast::Instruction::BarArrive {
+            data: ast::BarData { aligned, spelling: ast::BarSpelling::Barrier },
+            arguments: BarArriveArgs { src1: a, src2: b }
+        }
+    }
     //barrier{.cta}.red.popc{.aligned}.u32  d, a{, b}, {!}c;
     //barrier{.cta}.red.op{.aligned}.pred   p, a{, b}, {!}c;
     bar{.cta}.sync                  a{, b} => {
         let _ = cta;
         ast::Instruction::Bar {
-            data: ast::BarData { aligned: true },
+            data: ast::BarData { aligned: true, spelling: ast::BarSpelling::Bar },
             arguments: BarArgs { src1: a, src2: b }
         }
     }
-    //bar{.cta}.arrive    a, b;
+    bar{.cta}.arrive                a, b => {
+        let _ = cta;
+        ast::Instruction::BarArrive {
+            data: ast::BarData { aligned: true, spelling: ast::BarSpelling::Bar },
+            arguments: BarArriveArgs { src1: a, src2: b }
+        }
+    }
     //bar{.cta}.red.popc.u32  d, a{, b}, {!}c;
     //bar{.cta}.red.op.pred   p, a{, b}, {!}c;
     //.op = { .and, .or };
@@ -2796,6 +4185,13 @@ derive_parser!(
         if level_cache_hint || cache_policy.is_some() {
             state.errors.push(PtxError::Todo);
         }
+        if type_.kind() == ast::ScalarKind::Float {
+            check_atom_operands(state, &type_.into(), &d, &b);
+        }
+        // .inc and .dec wrap around the value operand, only defined for .u32
+        if matches!(op, RawAtomicOp::Inc | RawAtomicOp::Dec) && type_ != ScalarType::U32 {
+            state.errors.push(PtxError::SyntaxError);
+        }
         ast::Instruction::Atom {
             data: AtomDetails {
                 semantics: sem.map(Into::into).unwrap_or(AtomSemantics::Relaxed),
@@ -2808,6 +4204,9 @@ derive_parser!(
         }
     }
     atom{.sem}{.scope}{.space}.cas.cas_type                                                     d, [a], b, c => {
+        for operand in [&d, &b, &c] {
+            check_cas_operand(state, operand, cas_type);
+        }
         ast::Instruction::AtomCas {
             data: AtomCasDetails {
                 semantics: sem.map(Into::into).unwrap_or(AtomSemantics::Relaxed),
@@ -2837,13 +4236,15 @@ derive_parser!(
         if level_cache_hint || cache_policy.is_some() {
             state.errors.push(PtxError::Todo);
         }
+        let type_ = ast::Type::Vector(vec_32_bit.len().get(), f32);
+        check_atom_operands(state, &type_, &d, &b);
         ast::Instruction::Atom {
             data: AtomDetails {
                 semantics: sem.map(Into::into).unwrap_or(AtomSemantics::Relaxed),
                 scope: scope.unwrap_or(MemScope::Gpu),
                 space: global.unwrap_or(StateSpace::Generic),
                 op: ast::AtomicOp::new(float_op, f32.kind()),
-                type_: ast::Type::Vector(vec_32_bit.len().get(), f32)
+                type_
             },
             arguments: AtomArgs { dst: d, src1: a, src2: b }
         }
@@ -2852,13 +4253,15 @@ derive_parser!(
         if level_cache_hint || cache_policy.is_some() {
             state.errors.push(PtxError::Todo);
         }
+        let type_ = ast::Type::maybe_vector(vec_16_bit, half_word_type);
+        check_atom_operands(state, &type_, &d, &b);
         ast::Instruction::Atom {
             data: AtomDetails {
                 semantics: sem.map(Into::into).unwrap_or(AtomSemantics::Relaxed),
                 scope: scope.unwrap_or(MemScope::Gpu),
                 space: global.unwrap_or(StateSpace::Generic),
                 op: ast::AtomicOp::new(float_op, half_word_type.kind()),
-                type_: ast::Type::maybe_vector(vec_16_bit, half_word_type)
+                type_
             },
             arguments: AtomArgs { dst: d, src1: a, src2: b }
         }
@@ -2867,18 +4270,21 @@ derive_parser!(
         if level_cache_hint || cache_policy.is_some() {
             state.errors.push(PtxError::Todo);
         }
+        let type_ = ast::Type::maybe_vector(vec_32_bit, packed_type);
+        check_atom_operands(state, &type_, &d, &b);
         ast::Instruction::Atom {
             data: AtomDetails {
                 semantics: sem.map(Into::into).unwrap_or(AtomSemantics::Relaxed),
                 scope: scope.unwrap_or(MemScope::Gpu),
                 space: global.unwrap_or(StateSpace::Generic),
                 op: ast::AtomicOp::new(float_op, packed_type.kind()),
-                type_: ast::Type::maybe_vector(vec_32_bit, packed_type)
+                type_
             },
             arguments: AtomArgs { dst: d, src1: a, src2: b }
         }
     }
-    .space: StateSpace =            { .global, .shared{::cta, ::cluster} };
+    // .const is read-only, it's only accepted to report a better error
+    .space: StateSpace =            { .const, .global, .shared{::cta, ::cluster} };
     .sem: AtomSemantics =           { .relaxed, .acquire, .release, .acq_rel };
     .scope: MemScope =              { .cta, .cluster, .gpu, .sys };
     .op: RawAtomicOp =              { .and, .or, .xor,
@@ -3153,6 +4559,9 @@ derive_parser!(
 
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#integer-arithmetic-instructions-bfe
     bfe.type  d, a, b, c => {
+        if !bit_field_operands_in_range(type_, &b, &c) {
+            state.errors.push(PtxError::SyntaxError);
+        }
         ast::Instruction::Bfe {
             data: type_,
             arguments: BfeArgs { dst: d, src1: a, src2: b, src3: c },
@@ -3162,6 +4571,9 @@ derive_parser!(
 
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#integer-arithmetic-instructions-bfi
     bfi.type  f, a, b, c, d => {
+        if !bit_field_operands_in_range(type_, &c, &d) {
+            state.errors.push(PtxError::SyntaxError);
+        }
         ast::Instruction::Bfi {
             data: type_,
             arguments: BfiArgs { dst: f, src1: a, src2: b, src3: c, src4: d },
@@ -3188,6 +4600,35 @@ derive_parser!(
         }
     }
 
+    // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#logic-and-shift-instructions-lop3
+    // lop3.BoolOp.b32 d|p, a, b, c, immLut, q;
+    lop3.b32  d, a, b, c, lut => {
+        let lut = match lut {
+            ast::ParsedOperand::Imm(ImmediateValue::S64(lut)) if (0..=0xff).contains(&lut) => lut as u8,
+            ast::ParsedOperand::Imm(ImmediateValue::U64(lut)) if lut <= 0xff => lut as u8,
+            _ => {
+                state.errors.push(PtxError::SyntaxError);
+                0
+            }
+        };
+        ast::Instruction::Lop3 {
+            data: lut,
+            arguments: Lop3Args { dst: d, src1: a, src2: b, src3: c }
+        }
+    }
+
+    // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#logic-and-shift-instructions-shf
+    shf <= { shf(stream) }
+
+    // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#integer-arithmetic-instructions-sad
+    sad.type  d, a, b, c => {
+        ast::Instruction::Sad {
+            data: type_,
+            arguments: SadArgs { dst: d, src1: a, src2: b, src3: c }
+        }
+    }
+    .type: ScalarType = { .u16, .u32, .u64, .s16, .s32, .s64 };
+
     // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#parallel-synchronization-and-communication-instructions-activemask
     activemask.b32 d => {
         ast::Instruction::Activemask {
@@ -3208,6 +4649,66 @@ derive_parser!(
     //.op_restrict = { .mbarrier_init };
     //.to_proxykind::from_proxykind = {.tensormap::generic};
 
+    // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#data-movement-and-conversion-instructions-shfl-sync
+    // The membermask is parsed as optional only to give a better error when
+    // it's missing, the .sync form requires it
+    shfl.sync.mode.b32  d[|p], a, b, c{, membermask} => {
+        check_shfl_clamp(state, &c);
+        let membermask = membermask.unwrap_or_else(|| {
+            state.errors.push(PtxError::SyntaxError);
+            ParsedOperand::Imm(ast::ImmediateValue::U64(u32::MAX as u64))
+        });
+        ast::Instruction::ShflSync {
+            data: ast::ShflSyncDetails { mode: mode.into() },
+            arguments: ShflSyncArgs {
+                dst: d,
+                dst_pred: p,
+                src: a,
+                src_lane: b,
+                src_opts: c,
+                src_membermask: membermask
+            }
+        }
+    }
+    .mode: RawShuffleMode = { .up, .down, .bfly, .idx };
+
+    // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#scalar-video-instructions-vshl-vshr
+    // vop.dtype.atype.u32{.sat}.mode d, a, b{, c};
+    // .mode = { .clamp, .wrap };
+    vshl.dtype.atype.btype{.sat}.mode d, a, b{, c} => {
+        ast::Instruction::Video {
+            data: ast::VideoDetails {
+                op: ast::VideoOp::Shl(mode.into()),
+                dtype,
+                atype,
+                btype,
+                saturate: sat,
+            },
+            arguments: VideoArgs { dst: d, src1: a, src2: b, src3: c }
+        }
+    }
+    .dtype: ScalarType = { .u32, .s32 };
+    .atype: ScalarType = { .u32, .s32 };
+    .btype: ScalarType = { .u32 };
+    .mode: RawVideoShiftMode = { .clamp, .wrap };
+
+    vshr.dtype.atype.btype{.sat}.mode d, a, b{, c} => {
+        ast::Instruction::Video {
+            data: ast::VideoDetails {
+                op: ast::VideoOp::Shr(mode.into()),
+                dtype,
+                atype,
+                btype,
+                saturate: sat,
+            },
+            arguments: VideoArgs { dst: d, src1: a, src2: b, src3: c }
+        }
+    }
+    .dtype: ScalarType = { .u32, .s32 };
+    .atype: ScalarType = { .u32, .s32 };
+    .btype: ScalarType = { .u32 };
+    .mode: RawVideoShiftMode = { .clamp, .wrap };
+
     membar.level => {
         ast::Instruction::Membar { data: level }
     }
@@ -3234,11 +4735,12 @@ mod tests {
     #[test]
     fn sm_11() {
         let tokens = Token::lexer(".target sm_11")
-            .collect::<Result<Vec<_>, ()>>()
+            .collect::<Result<Vec<_>, _>>()
             .unwrap();
+        let mut errors = Vec::new();
         let stream = super::PtxParser {
             input: &tokens[..],
-            state: PtxParserState::new(),
+            state: PtxParserState::new(&mut errors),
         };
         assert_eq!(target.parse(stream).unwrap(), (11, None));
     }
@@ -3246,11 +4748,12 @@ mod tests {
     #[test]
     fn sm_90a() {
         let tokens = Token::lexer(".target sm_90a")
-            .collect::<Result<Vec<_>, ()>>()
+            .collect::<Result<Vec<_>, _>>()
             .unwrap();
+        let mut errors = Vec::new();
         let stream = super::PtxParser {
             input: &tokens[..],
-            state: PtxParserState::new(),
+            state: PtxParserState::new(&mut errors),
         };
         assert_eq!(target.parse(stream).unwrap(), (90, Some('a')));
     }
@@ -3258,12 +4761,4088 @@ mod tests {
     #[test]
     fn sm_90ab() {
         let tokens = Token::lexer(".target sm_90ab")
-            .collect::<Result<Vec<_>, ()>>()
+            .collect::<Result<Vec<_>, _>>()
             .unwrap();
+        let mut errors = Vec::new();
         let stream = super::PtxParser {
             input: &tokens[..],
-            state: PtxParserState::new(),
+            state: PtxParserState::new(&mut errors),
         };
         assert!(target.parse(stream).is_err());
     }
+
+    #[test]
+    fn remove_self_moves() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u32 r;
+                .reg .u32 s;
+                mov.u32 r, r;
+                mov.u32 r, s;
+                {
+                    mov.u32 s, s;
+                }
+                ret;
+            }";
+        let mut module = super::parse_module_checked(text).unwrap();
+        let func = match &mut module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        super::remove_self_moves(func);
+        let body = func.body.as_ref().unwrap();
+        let movs = body
+            .iter()
+            .filter_map(|statement| match statement {
+                super::Statement::Instruction(
+                    _,
+                    super::Instruction::Mov { arguments, .. },
+                ) => Some((arguments.dst.clone(), arguments.src.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            movs,
+            vec![(super::ParsedOperand::Reg("r"), super::ParsedOperand::Reg("s"))]
+        );
+        match body.iter().find(|s| matches!(s, super::Statement::Block(_))) {
+            Some(super::Statement::Block(block)) => assert!(block.is_empty()),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn dwarf_line() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            @@DWARF .byte 0x01
+            .visible .entry foobar()
+            {
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        assert_eq!(module.directives.len(), 2);
+        assert!(matches!(
+            module.directives[0],
+            super::Directive::DwarfLine(".byte 0x01")
+        ));
+    }
+
+    #[test]
+    fn tuning_directive_before_entry() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .maxntid 256 .entry foobar(.param .u64 a) .minnctapersm 4
+            {
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        assert!(matches!(
+            func.tuning[..],
+            [
+                super::TuningDirective::MaxNtid(256, 1, 1),
+                super::TuningDirective::MinNCtaPerSm(4)
+            ]
+        ));
+    }
+
+    #[test]
+    fn atom_float_operand_type() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u64 a;
+                .reg .f32 b;
+                .reg .f32 d;
+                atom.global.add.f32 d, [a], b;
+                ret;
+            }";
+        assert!(super::parse_module_checked(text).is_ok());
+    }
+
+    #[test]
+    fn atom_float_operand_type_mismatch() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u64 a;
+                .reg .b32 bInt;
+                .reg .f32 d;
+                atom.global.add.f32 d, [a], bInt;
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn atom_packed_half() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .align 4 .b8 buf[64];
+
+            .visible .entry foobar()
+            {
+                .reg .f16x2 b;
+                .reg .b32 bits;
+                .reg .f16x2 d;
+                atom.global.add.noftz.f16x2 d, [buf], b;
+                atom.global.add.noftz.f16x2 d, [buf+8], bits;
+                ret;
+            }";
+        assert!(super::parse_module_checked(text).is_ok());
+    }
+
+    #[test]
+    fn atom_half_in_bit_register() {
+        let text = "
+            .version 6.5
+            .target sm_70
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u64 a;
+                .reg .b16 h;
+                .reg .u16 u;
+                .reg .b32 p<2>;
+                atom.global.add.noftz.f16 h, [a], h;
+                atom.global.add.noftz.bf16 u, [a], h;
+                atom.global.add.noftz.v2.f16x2 {p0, p1}, [a], {p0, p1};
+                ret;
+            }";
+        assert!(super::parse_module_checked(text).is_ok());
+    }
+
+    #[test]
+    fn atom_packed_half_scalar_value() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u64 a;
+                .reg .f16 b;
+                .reg .f16x2 d;
+                atom.global.add.noftz.f16x2 d, [a], b;
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn generic_operand() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .u32 myGlobal;
+
+            .visible .entry foobar()
+            {
+                .reg .u64 r;
+                mov.u64 r, generic(myGlobal);
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[1] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        match &func.body.as_ref().unwrap()[1] {
+            super::Statement::Instruction(_, super::Instruction::Mov { arguments, .. }) => {
+                assert_eq!(arguments.src, super::ParsedOperand::Generic("myGlobal"))
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn opcode_named_label_and_register() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u32 %add;
+                .reg .u32 r;
+            mov:
+                add.u32 r, %add, %add;
+                bra mov;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let body = func.body.as_ref().unwrap();
+        assert!(matches!(body[2], super::Statement::Label("mov")));
+        match &body[3] {
+            super::Statement::Instruction(_, super::Instruction::Add { arguments, .. }) => {
+                assert_eq!(arguments.src1, super::ParsedOperand::Reg("%add"));
+                assert_eq!(arguments.src2, super::ParsedOperand::Reg("%add"));
+            }
+            _ => panic!(),
+        }
+        match &body[4] {
+            super::Statement::Instruction(_, super::Instruction::Bra { arguments, .. }) => {
+                assert_eq!(arguments.src, "mov")
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn st_weak_explicit() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u64 a;
+                .reg .u32 b;
+                st.weak.global.u32 [a], b;
+                st.global.u32 [a], b;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let weak_explicit = func
+            .body
+            .as_ref()
+            .unwrap()
+            .iter()
+            .filter_map(|statement| match statement {
+                super::Statement::Instruction(_, super::Instruction::St { data, .. }) => {
+                    assert!(data.qualifier == super::LdStQualifier::Weak);
+                    Some(data.weak_explicit)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(weak_explicit, vec![true, false]);
+    }
+
+    #[test]
+    fn setp_distinct_destinations() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .s32 a;
+                .reg .s32 b;
+                .reg .pred p;
+                .reg .pred q;
+                setp.lt.s32 p|q, a, b;
+                ret;
+            }";
+        assert!(super::parse_module_checked(text).is_ok());
+    }
+
+    #[test]
+    fn setp_same_destinations() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .s32 a;
+                .reg .s32 b;
+                .reg .pred p;
+                setp.lt.s32 p|p, a, b;
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn param_access_around_call() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .func (.param .u32 ret) foo(.param .u32 x)
+            {
+                .reg .u32 r;
+                ld.param.u32 r, [x];
+                st.param.u32 [ret], r;
+                ret;
+            }
+
+            .visible .entry foobar(.param .u32 input)
+            {
+                .reg .u32 r;
+                ld.param.u32 r, [input];
+                {
+                    .param .u32 arg;
+                    .param .u32 retval;
+                    st.param.u32 [arg], r;
+                    call (retval), foo, (arg);
+                    ld.param.u32 r, [retval];
+                }
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_some());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn param_access_undeclared() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u32 r;
+                ld.param.u32 r, [undeclared];
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_some());
+        assert!(matches!(errors[..], [super::PtxError::UnexpectedParamAccess]));
+        assert!(super::parse_module_checked(text).is_ok());
+    }
+
+    #[test]
+    fn for_each_operand_mut() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u32 %r<3>;
+                .reg .u64 %rd<2>;
+                add.u32 %r0, %r0, %r2;
+                ld.global.u32 %r0, [%rd0+4];
+                ret;
+            }";
+        let mut module = super::parse_module_checked(text).unwrap();
+        let func = match &mut module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let mut operands = Vec::new();
+        for statement in func.body.as_mut().unwrap().iter_mut() {
+            if let super::Statement::Instruction(_, instruction) = statement {
+                instruction.for_each_operand_mut(|operand| match operand {
+                    super::ParsedOperand::Reg(name) | super::ParsedOperand::RegOffset(name, _) => {
+                        *name = match *name {
+                            "%r0" => "%r1",
+                            "%rd0" => "%rd1",
+                            name => name,
+                        };
+                        operands.push(operand.clone());
+                    }
+                    _ => {}
+                });
+            }
+        }
+        assert_eq!(
+            operands,
+            vec![
+                super::ParsedOperand::Reg("%r1"),
+                super::ParsedOperand::Reg("%r1"),
+                super::ParsedOperand::Reg("%r2"),
+                super::ParsedOperand::Reg("%r1"),
+                super::ParsedOperand::RegOffset("%rd1", 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn rename_registers() {
+        let map = [("%rd0", "%rd9".to_string()), ("%r0", "%r9".to_string())]
+            .into_iter()
+            .collect::<std::collections::HashMap<_, _>>();
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u64 %rd0;
+                .reg .u32 %r0;
+                ld.global.u32 %r0, [%rd0];
+                st.global.u32 [%rd0+4], %r0;
+                ret;
+            }";
+        let mut module = super::parse_module_checked(text).unwrap();
+        let func = match &mut module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        super::rename_registers(func, &map);
+        let body = func.body.as_ref().unwrap();
+        match &body[0] {
+            super::Statement::Variable(var) => assert_eq!(var.var.name, "%rd9"),
+            _ => panic!(),
+        }
+        match &body[2] {
+            super::Statement::Instruction(_, super::Instruction::Ld { arguments, .. }) => {
+                assert_eq!(arguments.dst, super::ParsedOperand::Reg("%r9"));
+                assert_eq!(arguments.src, super::ParsedOperand::Reg("%rd9"));
+            }
+            _ => panic!(),
+        }
+        match &body[3] {
+            super::Statement::Instruction(_, super::Instruction::St { arguments, .. }) => {
+                assert_eq!(arguments.src1, super::ParsedOperand::RegOffset("%rd9", 4));
+                assert_eq!(arguments.src2, super::ParsedOperand::Reg("%r9"));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn rename_registers_only() {
+        let map = [
+            ("%r0", "%r9".to_string()),
+            ("L", "M".to_string()),
+            ("g", "h".to_string()),
+        ]
+        .into_iter()
+        .collect::<std::collections::HashMap<_, _>>();
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .align 4 .b8 g[8];
+
+            .visible .entry foobar()
+            {
+                .reg .u64 %rd0;
+                .reg .u32 %r0;
+                mov.u64 %rd0, g+4;
+                ld.global.u32 %r0, [%rd0];
+                bra L;
+            L:
+                ret;
+            }";
+        let mut module = super::parse_module_checked(text).unwrap();
+        let func = match &mut module.directives[1] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        super::rename_registers(func, &map);
+        let body = func.body.as_ref().unwrap();
+        match &body[2] {
+            super::Statement::Instruction(_, super::Instruction::Mov { arguments, .. }) => {
+                assert_eq!(arguments.src, super::ParsedOperand::SymbolOffset("g", 4));
+            }
+            _ => panic!(),
+        }
+        match &body[3] {
+            super::Statement::Instruction(_, super::Instruction::Ld { arguments, .. }) => {
+                assert_eq!(arguments.dst, super::ParsedOperand::Reg("%r9"));
+            }
+            _ => panic!(),
+        }
+        match &body[4] {
+            super::Statement::Instruction(_, super::Instruction::Bra { arguments, .. }) => {
+                assert_eq!(arguments.src, "L");
+            }
+            _ => panic!(),
+        }
+        assert!(matches!(body[5], super::Statement::Label("L")));
+    }
+
+    #[test]
+    fn cvt_tf32_single_input() {
+        let text = "
+            .version 7.0
+            .target sm_80
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .f32 a;
+                .reg .b32 d;
+                cvt.rna.tf32.f32 d, a;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        match &func.body.as_ref().unwrap()[2] {
+            super::Statement::Instruction(_, super::Instruction::Cvt { data, arguments }) => {
+                assert!(matches!(
+                    data.mode,
+                    super::CvtMode::Tf32 {
+                        rounding: super::Tf32RoundingMode::NearestAway,
+                        relu: false,
+                        saturate_finite: false
+                    }
+                ));
+                assert_eq!(arguments.dst, super::ParsedOperand::Reg("d"));
+                assert_eq!(arguments.src, super::ParsedOperand::Reg("a"));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn unsupported_modifier_strictness() {
+        let text = "
+            .version 7.4
+            .target sm_80
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u64 a;
+                .reg .u32 d;
+                ld.global.L2::128B.u32 d, [a];
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        let body_len = match &module.unwrap().directives[0] {
+            super::Directive::Method(_, func) => func.body.as_ref().unwrap().len(),
+            _ => panic!(),
+        };
+        assert_eq!(body_len, 4);
+        assert!(matches!(errors[..], [super::PtxError::Todo]));
+        let super::ParseResult { module, errors, .. } = super::parse_module_with_options(
+            text,
+            super::ParseOptions {
+                strict_unsupported: true,
+                ..Default::default()
+            },
+        );
+        assert!(module.is_none());
+        assert!(matches!(errors[..], [super::PtxError::Todo]));
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::Todo]));
+    }
+
+    #[test]
+    fn ld_global_nc() {
+        let text = "
+            .version 6.5
+            .target sm_35
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u64 a;
+                .reg .f32 d;
+                ld.global.nc.f32 d, [a];
+                ret;
+            }";
+        assert!(super::parse_module_checked(text).is_ok());
+    }
+
+    #[test]
+    fn ld_shared_nc() {
+        let text = "
+            .version 6.5
+            .target sm_35
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u64 a;
+                .reg .f32 d;
+                ld.shared.nc.f32 d, [a];
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn mad_carry() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u32 a;
+                .reg .u32 b;
+                .reg .u32 c;
+                .reg .u32 d;
+                mad.lo.u32 d, a, b, c;
+                mad.lo.cc.u32 d, a, b, c;
+                madc.lo.cc.u32 d, a, b, c;
+                madc.hi.u32 d, a, b, c;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let carries = func
+            .body
+            .as_ref()
+            .unwrap()
+            .iter()
+            .filter_map(|statement| match statement {
+                super::Statement::Instruction(
+                    _,
+                    super::Instruction::Mad {
+                        data:
+                            super::MadDetails::Integer {
+                                carry_in,
+                                carry_out,
+                                ..
+                            },
+                        ..
+                    },
+                ) => Some((*carry_in, *carry_out)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            carries,
+            vec![(false, false), (false, true), (true, true), (true, false)]
+        );
+    }
+
+    #[test]
+    fn vshl_clamp() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .u32 d;
+                .reg .u32 a;
+                .reg .u32 b;
+                .reg .u32 c;
+                vshl.u32.u32.u32.clamp d, a, b, c;
+                vshr.s32.s32.u32.sat.wrap d, a, b, c;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let body = func.body.as_ref().unwrap();
+        match &body[4] {
+            super::Statement::Instruction(None, super::Instruction::Video { data, .. }) => {
+                assert!(matches!(
+                    data.op,
+                    super::VideoOp::Shl(super::VideoShiftMode::Clamp)
+                ));
+                assert!(!data.saturate);
+            }
+            _ => panic!(),
+        }
+        match &body[5] {
+            super::Statement::Instruction(None, super::Instruction::Video { data, .. }) => {
+                assert!(matches!(
+                    data.op,
+                    super::VideoOp::Shr(super::VideoShiftMode::Wrap)
+                ));
+                assert!(data.dtype == super::ScalarType::S32);
+                assert!(data.saturate);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn video_shift_saturate_before_mode() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .s32 d;
+                .reg .s32 a;
+                .reg .u32 b;
+                vshr.s32.s32.u32.sat.wrap d, a, b;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        match &func.body.as_ref().unwrap()[3] {
+            super::Statement::Instruction(
+                None,
+                super::Instruction::Video { data, arguments },
+            ) => {
+                assert!(data.saturate);
+                assert!(arguments.src3.is_none());
+            }
+            _ => panic!(),
+        }
+        let text = text.replace(".sat.wrap", ".wrap.sat");
+        assert!(super::parse_module_checked(&text).is_err());
+    }
+
+    #[test]
+    fn extern_func_declaration_and_call() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .extern .func (.param .b32 retval) foo (.param .b32 arg);
+
+            .visible .entry bar()
+            {
+                .reg .b32 r;
+                {
+                    .param .b32 param0;
+                    st.param.b32 [param0], r;
+                    .param .b32 retval0;
+                    call (retval0), foo, (param0);
+                    ld.param.b32 r, [retval0];
+                }
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        match &module.directives[0] {
+            super::Directive::Method(linking, func) => {
+                assert!(linking.contains(super::LinkingDirective::EXTERN));
+                assert!(func.body.is_none());
+            }
+            _ => panic!(),
+        }
+        match &module.directives[1] {
+            super::Directive::Method(_, func) => assert!(func.body.is_some()),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn predicated_prmt() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .pred p;
+                .reg .b32 d;
+                .reg .b32 a;
+                .reg .b32 b;
+                .reg .b32 c;
+                @p prmt.b32 d, a, b, c;
+                @!p prmt.b32 d, a, b, 0x3210;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let body = func.body.as_ref().unwrap();
+        match &body[5] {
+            super::Statement::Instruction(
+                Some(super::PredAt { not: false, label: "p" }),
+                super::Instruction::PrmtSlow { .. },
+            ) => {}
+            _ => panic!(),
+        }
+        match &body[6] {
+            super::Statement::Instruction(
+                Some(super::PredAt { not: true, label: "p" }),
+                super::Instruction::Prmt { data: 0x3210, .. },
+            ) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn align_out_of_range() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .align 4294967296 .b8 foo[4];";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(
+            errors[0],
+            super::PtxError::LiteralOutOfRange {
+                expected_type: "u32",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn maxnreg_out_of_range() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            .maxnreg 4294967296
+            {
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        match &errors[..] {
+            [super::PtxError::LiteralOutOfRange {
+                value,
+                expected_type: "u32",
+            }] => assert_eq!(value, "4294967296"),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn align_power_of_two() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .align 16 .b8 foo[16];";
+        let module = super::parse_module_checked(text).unwrap();
+        match &module.directives[0] {
+            super::Directive::Variable(_, var) => assert_eq!(var.align, Some(16)),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn align_not_power_of_two() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .align 3 .b8 foo[16];";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn cvt_relu_integer_destination() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .s32 d;
+                .reg .f32 a;
+                cvt.rn.relu.s32.f32 d, a;
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn cvt_saturate_narrowing() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .s8 d;
+                .reg .s32 a;
+                cvt.sat.s8.s32 d, a;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        match &func.body.as_ref().unwrap()[2] {
+            super::Statement::Instruction(None, super::Instruction::Cvt { data, .. }) => {
+                assert!(matches!(data.mode, super::CvtMode::Truncate));
+                assert!(data.saturate);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn cvt_saturate_widening() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .s32 d;
+                .reg .s8 a;
+                cvt.sat.s32.s8 d, a;
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn cvt_relu_pack() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b32 d;
+                .reg .f32 a;
+                .reg .f32 b;
+                cvt.rn.relu.f16x2.f32 d, a, b;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        match &func.body.as_ref().unwrap()[3] {
+            super::Statement::Instruction(None, super::Instruction::CvtPack { data, .. }) => {
+                assert!(data.to == super::ScalarType::F16x2);
+                assert!(data.rounding == super::RoundingMode::NearestEven);
+                assert!(!data.flush_to_zero);
+                assert!(data.relu);
+                assert!(!data.saturate_finite);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn display_register_operands() {
+        assert_eq!(super::ParsedOperand::Reg("r1").to_string(), "r1");
+        assert_eq!(super::ParsedOperand::RegOffset("rd", 8).to_string(), "rd+8");
+        assert_eq!(super::ParsedOperand::RegOffset("rd", -8).to_string(), "rd+-8");
+        assert_eq!(super::ParsedOperand::VecMember("v", 0).to_string(), "v.x");
+        assert_eq!(super::ParsedOperand::VecMember("v", 3).to_string(), "v.w");
+        assert_eq!(
+            super::ParsedOperand::VecPack(vec!["a", "b", "c", "d"]).to_string(),
+            "{a, b, c, d}"
+        );
+        assert_eq!(super::ParsedOperand::Generic("foo").to_string(), "generic(foo)");
+    }
+
+    #[test]
+    fn display_immediate_operands() {
+        let imm = |value| super::ParsedOperand::<&str>::Imm(value).to_string();
+        assert_eq!(imm(super::ImmediateValue::S64(-3)), "-3");
+        assert_eq!(imm(super::ImmediateValue::U64(3)), "3U");
+        assert_eq!(imm(super::ImmediateValue::F32(1.0)), "0f3F800000");
+        assert_eq!(imm(super::ImmediateValue::F64(1.0)), "0d3FF0000000000000");
+    }
+
+    #[test]
+    fn display_pred_at() {
+        let pred = |not| super::PredAt { not, label: "p" }.to_string();
+        assert_eq!(pred(false), "@p");
+        assert_eq!(pred(true), "@!p");
+    }
+
+    #[test]
+    fn bracketed_offsets() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .u32 r;
+                .reg .u64 a;
+                ld.global.u32 r, [a + 8];
+                ld.global.u32 r, [a+-8];
+                st.global.u32 [a + -2147483648], r;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let body = func.body.as_ref().unwrap();
+        match &body[2] {
+            super::Statement::Instruction(_, super::Instruction::Ld { arguments, .. }) => {
+                assert_eq!(arguments.src, super::ParsedOperand::RegOffset("a", 8));
+            }
+            _ => panic!(),
+        }
+        match &body[3] {
+            super::Statement::Instruction(_, super::Instruction::Ld { arguments, .. }) => {
+                assert_eq!(arguments.src, super::ParsedOperand::RegOffset("a", -8));
+            }
+            _ => panic!(),
+        }
+        match &body[4] {
+            super::Statement::Instruction(_, super::Instruction::St { arguments, .. }) => {
+                assert_eq!(arguments.src1, super::ParsedOperand::RegOffset("a", i32::MIN));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn b128_unaligned_address() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .align 4 .b8 buf[64];
+
+            .entry foobar()
+            {
+                .reg .b128 r;
+                ld.global.b128 r, [buf+16];
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_some());
+        assert!(matches!(errors[..], [super::PtxError::MisalignedAccess]));
+    }
+
+    #[test]
+    fn v4_b32_aligned_address() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .align 16 .b8 buf[64];
+
+            .entry foobar()
+            {
+                .reg .b32 a;
+                .reg .b32 b;
+                .reg .b32 c;
+                .reg .b32 d;
+                .reg .u64 addr;
+                ld.global.v4.b32 {a, b, c, d}, [buf+32];
+                ld.global.v4.b32 {a, b, c, d}, [addr+4];
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_some());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn call_wrong_return_count() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .func (.param .b32 retval) foo (.param .b32 arg);
+
+            .entry bar()
+            {
+                .param .b32 param0;
+                .param .b32 retval0;
+                .param .b32 retval1;
+                call (retval0, retval1), foo, (param0);
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::MalformedCall]));
+    }
+
+    #[test]
+    fn call_same_size_argument_types() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .func (.reg .b64 out) foo (.reg .b64 arg);
+
+            .entry bar()
+            {
+                .reg .u64 a;
+                .reg .u64 b;
+                call (b), foo, (a);
+                ret;
+            }";
+        assert!(super::parse_module_checked(text).is_ok());
+    }
+
+    #[test]
+    fn call_wrong_return_type() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .func (.param .b32 retval) foo (.param .b32 arg);
+
+            .entry bar()
+            {
+                .param .b32 param0;
+                .param .b64 retval0;
+                call (retval0), foo, (param0);
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn struct_parameter_offset() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .func foo (.param .align 4 .b8 s[8])
+            {
+                .reg .u32 r;
+                ld.param.u32 r, [s+4];
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(errors.is_empty());
+        let module = module.unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        assert!(matches!(
+            func.func_directive.input_arguments[0].v_type,
+            super::Type::Array(None, super::ScalarType::B8, ref dims) if dims[..] == [8]
+        ));
+        match &func.body.as_ref().unwrap()[1] {
+            super::Statement::Instruction(None, super::Instruction::Ld { data, arguments }) => {
+                assert!(data.state_space == super::StateSpace::Param);
+                assert_eq!(arguments.src, super::ParsedOperand::RegOffset("s", 4));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn entry_with_tuning_and_body() {
+        let text = "
+            .version 7.0
+            .target sm_70
+            .address_size 64
+
+            .visible .entry foo(
+                .param .u64 input,
+                .param .u64 output
+            )
+            .maxntid 256
+            .minnctapersm 2
+            {
+                .reg .u64 in_addr;
+                .reg .u64 out_addr;
+                .reg .u64 temp;
+
+                ld.param.u64 in_addr, [input];
+                ld.param.u64 out_addr, [output];
+                ld.u64 temp, [in_addr];
+                add.u64 temp, temp, 1;
+                st.u64 [out_addr], temp;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let (linking, func) = match &module.directives[0] {
+            super::Directive::Method(linking, func) => (linking, func),
+            _ => panic!(),
+        };
+        assert!(linking.contains(super::LinkingDirective::VISIBLE));
+        assert!(matches!(
+            func.func_directive.name,
+            super::MethodName::Kernel("foo")
+        ));
+        assert_eq!(func.func_directive.input_arguments.len(), 2);
+        assert!(matches!(
+            func.tuning[..],
+            [
+                super::TuningDirective::MaxNtid(256, 1, 1),
+                super::TuningDirective::MinNCtaPerSm(2)
+            ]
+        ));
+        let body = func.body.as_ref().unwrap();
+        assert_eq!(body.len(), 9);
+        assert!(matches!(
+            body[8],
+            super::Statement::Instruction(None, super::Instruction::Ret { .. })
+        ));
+    }
+
+    #[test]
+    fn setp_bool_op_source_type() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .pred p;
+                .reg .pred c;
+                .reg .s32 a;
+                .reg .s32 b;
+                .reg .b32 rInt;
+                setp.gt.and.s32 p, a, b, c;
+                setp.gt.and.s32 p, a, b, !c;
+                setp.gt.and.s32 p, a, b, rInt;
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn instruction_classification() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .u32 r;
+                .reg .u64 a;
+                add.u32 r, r, 1;
+                ld.global.u32 r, [a];
+                bar.sync 0;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let classes = func.body.as_ref().unwrap()[2..]
+            .iter()
+            .map(|statement| match statement {
+                super::Statement::Instruction(_, instruction) => (
+                    instruction.is_terminator(),
+                    instruction.is_memory(),
+                    instruction.is_barrier(),
+                ),
+                _ => panic!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            classes,
+            [
+                (false, false, false),
+                (false, true, false),
+                (false, false, true),
+                (true, false, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn relaxed_scopes() {
+        let text = "
+            .version 7.8
+            .target sm_90
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .u32 r;
+                .reg .u64 a;
+                ld.relaxed.cta.global.u32 r, [a];
+                ld.global.relaxed.cluster.u32 r, [a];
+                ld.relaxed.gpu.global.u32 r, [a];
+                ld.relaxed.sys.global.u32 r, [a];
+                st.relaxed.cta.shared.u32 [a], r;
+                st.relaxed.cluster.shared::cluster.u32 [a], r;
+                st.relaxed.gpu.shared.u32 [a], r;
+                st.relaxed.sys.u32 [a], r;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let scopes = func.body.as_ref().unwrap()[2..10]
+            .iter()
+            .map(|statement| match statement {
+                super::Statement::Instruction(_, super::Instruction::Ld { data, .. }) => {
+                    data.qualifier
+                }
+                super::Statement::Instruction(_, super::Instruction::St { data, .. }) => {
+                    data.qualifier
+                }
+                _ => panic!(),
+            })
+            .collect::<Vec<_>>();
+        use super::{LdStQualifier::Relaxed, MemScope::*};
+        assert!(
+            scopes
+                == [
+                    Relaxed(Cta),
+                    Relaxed(Cluster),
+                    Relaxed(Gpu),
+                    Relaxed(Sys),
+                    Relaxed(Cta),
+                    Relaxed(Cluster),
+                    Relaxed(Gpu),
+                    Relaxed(Sys)
+                ]
+        );
+    }
+
+    #[test]
+    fn relaxed_shared_sys_scope() {
+        let text = "
+            .version 7.8
+            .target sm_90
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .u32 r;
+                .reg .u64 a;
+                ld.relaxed.sys.shared.u32 r, [a];
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn acquire_gpu_scope() {
+        let text = "
+            .version 7.0
+            .target sm_70
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .u32 r;
+                .reg .u64 a;
+                ld.acquire.gpu.global.u32 r, [a];
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        match &func.body.as_ref().unwrap()[2] {
+            super::Statement::Instruction(None, super::Instruction::Ld { data, .. }) => {
+                assert!(data.qualifier == super::LdStQualifier::Acquire(super::MemScope::Gpu));
+                assert!(data.state_space == super::StateSpace::Global);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn acquire_release_scopes() {
+        let text = "
+            .version 7.0
+            .target sm_70
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .u32 r;
+                .reg .u64 a;
+                ld.acquire.cta.global.u32 r, [a];
+                st.release.sys.global.u32 [a], r;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let body = func.body.as_ref().unwrap();
+        match &body[2] {
+            super::Statement::Instruction(None, super::Instruction::Ld { data, .. }) => {
+                assert!(data.qualifier == super::LdStQualifier::Acquire(super::MemScope::Cta));
+                assert!(data.state_space == super::StateSpace::Global);
+            }
+            _ => panic!(),
+        }
+        match &body[3] {
+            super::Statement::Instruction(None, super::Instruction::St { data, .. }) => {
+                assert!(data.qualifier == super::LdStQualifier::Release(super::MemScope::Sys));
+                assert!(data.state_space == super::StateSpace::Global);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn scalar_type_variants() {
+        use super::ScalarType::*;
+        let types = [
+            (".s8", S8),
+            (".s16", S16),
+            (".s16x2", S16x2),
+            (".s32", S32),
+            (".s64", S64),
+            (".u8", U8),
+            (".u16", U16),
+            (".u16x2", U16x2),
+            (".u32", U32),
+            (".u64", U64),
+            (".b8", B8),
+            (".b16", B16),
+            (".b32", B32),
+            (".b64", B64),
+            (".b128", B128),
+            (".pred", Pred),
+            (".f16", F16),
+            (".f16x2", F16x2),
+            (".f32", F32),
+            (".f64", F64),
+            (".bf16", BF16),
+            (".bf16x2", BF16x2),
+        ];
+        let declarations = types
+            .iter()
+            .enumerate()
+            .map(|(index, (name, _))| format!(".reg {} r{};", name, index))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let text = format!(
+            "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {{
+                {}
+                ret;
+            }}",
+            declarations
+        );
+        let module = super::parse_module_checked(&text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let body = func.body.as_ref().unwrap();
+        assert_eq!(body.len(), types.len() + 1);
+        for (statement, (_, type_)) in body.iter().zip(types.iter()) {
+            match statement {
+                super::Statement::Variable(var) => {
+                    assert!(var.var.v_type == super::Type::Scalar(*type_))
+                }
+                _ => panic!(),
+            }
+        }
+    }
+
+    #[test]
+    fn variable_state_spaces() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b32 a;
+                .local .b32 b;
+                .param .b32 c;
+                .global .b32 d;
+                .const .b32 e;
+                .shared .b32 f;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let spaces = func.body.as_ref().unwrap()[..6]
+            .iter()
+            .map(|statement| match statement {
+                super::Statement::Variable(var) => var.var.state_space,
+                _ => panic!(),
+            })
+            .collect::<Vec<_>>();
+        use super::StateSpace::*;
+        assert!(spaces == [Reg, Local, Param, Global, Const, Shared]);
+    }
+
+    #[test]
+    fn extern_dynamic_shared_array() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .extern .shared .align 16 .b8 dynArr[];";
+        let module = super::parse_module_checked(text).unwrap();
+        match &module.directives[0] {
+            super::Directive::Variable(linking, var) => {
+                assert!(linking.contains(super::LinkingDirective::EXTERN));
+                assert!(var.state_space == super::StateSpace::Shared);
+                assert!(matches!(
+                    var.v_type,
+                    super::Type::Array(None, super::ScalarType::B8, ref dims) if dims[..] == [0]
+                ));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn non_extern_unsized_array() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .shared .align 16 .b8 dynArr[];";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::NonExternPointer]));
+    }
+
+    #[test]
+    fn zero_dimension_array() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .b8 x[0];
+            .global .b8 y[4][0];";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(
+            errors[..],
+            [
+                super::PtxError::ZeroDimensionArray,
+                super::PtxError::ZeroDimensionArray
+            ]
+        ));
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .extern .global .b8 x[];";
+        assert!(super::parse_module_checked(text).is_ok());
+    }
+
+    #[test]
+    fn multi_dimensional_register_array() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b32 x[4][4];
+                .local .b32 y[4][4];
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::MultiArrayVariable]));
+        let text = text.replace("x[4][4]", "x[4]");
+        let errors = super::parse_module_checked(&text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::RegisterArray]));
+    }
+
+    #[test]
+    fn predicate_array() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .pred p[4];";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::WrongArrayType]));
+    }
+
+    #[test]
+    fn packed_integer_add_rounding() {
+        let text = "
+            .version 8.0
+            .target sm_90
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b32 d;
+                .reg .b32 a;
+                .reg .b32 b;
+                add.s16x2 d, a, b;
+                sub.u16x2 d, a, b;
+                ret;
+            }";
+        assert!(super::parse_module_checked(text).is_ok());
+        let text = "
+            .version 8.0
+            .target sm_90
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b32 d;
+                .reg .b32 a;
+                .reg .b32 b;
+                add.rn.s16x2 d, a, b;
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn special_register_dimensions() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .u32 r;
+                mov.u32 r, %tid.x;
+                mov.u32 r, %ctaid.y;
+                mov.u32 r, %ntid.z;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let body = func.body.as_ref().unwrap();
+        let sources = body[1..4]
+            .iter()
+            .map(|statement| match statement {
+                super::Statement::Instruction(None, super::Instruction::Mov { arguments, .. }) => {
+                    arguments.src.clone()
+                }
+                _ => panic!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            vec![
+                super::ParsedOperand::SpecialRegisterDimension("%tid", 0),
+                super::ParsedOperand::SpecialRegisterDimension("%ctaid", 1),
+                super::ParsedOperand::SpecialRegisterDimension("%ntid", 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn shared_array_at_both_scopes() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .shared .align 4 .b32 buffer[64];
+
+            .entry foobar()
+            {
+                .shared .align 4 .b32 buffer[64];
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let module_var = match &module.directives[0] {
+            super::Directive::Variable(_, var) => var,
+            _ => panic!(),
+        };
+        let func = match &module.directives[1] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let body = func.body.as_ref().unwrap();
+        let local_var = match &body[0] {
+            super::Statement::Variable(multi_var) => &multi_var.var,
+            _ => panic!(),
+        };
+        for var in [module_var, local_var] {
+            assert_eq!(var.name, "buffer");
+            assert_eq!(var.align, Some(4));
+            assert!(var.state_space == super::StateSpace::Shared);
+            assert!(var.v_type == super::Type::Array(None, super::ScalarType::B32, vec![64]));
+        }
+    }
+
+    #[test]
+    fn vector_ld_st() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar(.param .u64 input)
+            {
+                .reg .u64 p;
+                .reg .f32 a;
+                .reg .f32 b;
+                .reg .u32 w<8>;
+                ld.param.u64 p, [input];
+                ld.global.v2.f32 {a, b}, [p];
+                st.global.v4.u32 [p], {w0, w1, w2, w3};
+                ld.global.v8.u32 {w0, w1, w2, w3, w4, w5, w6, w7}, [p];
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let body = func.body.as_ref().unwrap();
+        match &body[5] {
+            super::Statement::Instruction(None, super::Instruction::Ld { arguments, .. }) => {
+                assert_eq!(arguments.dst, super::ParsedOperand::VecPack(vec!["a", "b"]));
+            }
+            _ => panic!(),
+        }
+        match &body[6] {
+            super::Statement::Instruction(None, super::Instruction::St { arguments, .. }) => {
+                assert_eq!(
+                    arguments.src2,
+                    super::ParsedOperand::VecPack(vec!["w0", "w1", "w2", "w3"])
+                );
+            }
+            _ => panic!(),
+        }
+        match &body[7] {
+            super::Statement::Instruction(None, super::Instruction::Ld { arguments, .. }) => {
+                assert!(matches!(&arguments.dst, super::ParsedOperand::VecPack(regs) if regs.len() == 8));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn call_sequence() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .func (.param .b32 ret) inc(.param .b32 x)
+            {
+                .reg .b32 r;
+                ld.param.b32 r, [x];
+                add.u32 r, r, 1;
+                st.param.b32 [ret], r;
+                ret;
+            }
+
+            .entry foobar()
+            {
+                .reg .b32 a;
+                .reg .b32 b;
+                mov.b32 a, 1;
+                {
+                    .param .b32 param0;
+                    st.param.b32 [param0+0], a;
+                    .param .b32 retval0;
+                    call.uni (retval0), inc, (param0);
+                    ld.param.b32 b, [retval0+0];
+                }
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[1] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let sites = super::call_sequences(func);
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].call.func, "inc");
+        match &*sites[0].setup {
+            [super::Instruction::St { arguments, .. }] => {
+                assert_eq!(arguments.src1, super::ParsedOperand::RegOffset("param0", 0));
+                assert_eq!(arguments.src2, super::ParsedOperand::Reg("a"));
+            }
+            _ => panic!(),
+        }
+        match &*sites[0].teardown {
+            [super::Instruction::Ld { arguments, .. }] => {
+                assert_eq!(arguments.dst, super::ParsedOperand::Reg("b"));
+                assert_eq!(arguments.src, super::ParsedOperand::RegOffset("retval0", 0));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn call_sequence_return_reads() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .func (.param .b32 ret) inc(.param .b32 x)
+            {
+                .reg .b32 r;
+                ld.param.b32 r, [x];
+                add.u32 r, r, 1;
+                st.param.b32 [ret], r;
+                ret;
+            }
+
+            .entry foobar(.param .b32 input)
+            {
+                .reg .b32 a;
+                .reg .b32 b;
+                .reg .b32 c;
+                .param .b32 param0;
+                .param .b32 retval0;
+                .param .b32 retval1;
+                ld.param.b32 a, [input];
+                st.param.b32 [param0], a;
+                call (retval0), inc, (param0);
+                ld.param.b32 b, [retval0];
+                call (retval1), inc, (param0);
+                ld.param.b32 a, [input];
+                ld.param.b32 c, [retval1];
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[1] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let sites = super::call_sequences(func);
+        assert_eq!(sites.len(), 2);
+        let return_reads = sites
+            .iter()
+            .map(|site| {
+                site.teardown
+                    .iter()
+                    .map(|instruction| match instruction {
+                        super::Instruction::Ld { arguments, .. } => {
+                            (arguments.dst.clone(), arguments.src.clone())
+                        }
+                        _ => panic!(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            return_reads,
+            [
+                vec![(super::ParsedOperand::Reg("b"), super::ParsedOperand::Reg("retval0"))],
+                vec![(super::ParsedOperand::Reg("c"), super::ParsedOperand::Reg("retval1"))],
+            ]
+        );
+    }
+
+    #[test]
+    fn future_version() {
+        let text = "
+            .version 99.0
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert_eq!(module.unwrap().version, (99, 0));
+        assert!(matches!(
+            errors[..],
+            [super::PtxError::UnsupportedVersion { major: 99, minor: 0 }]
+        ));
+    }
+
+    #[test]
+    fn unknown_minor_version() {
+        let text = "
+            .version 7.9
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert_eq!(module.unwrap().version, (7, 9));
+        assert!(matches!(
+            errors[..],
+            [super::PtxError::UnsupportedVersion { major: 7, minor: 9 }]
+        ));
+        let text = text.replace("7.9", "7.8");
+        assert!(super::parse_module(&text).errors.is_empty());
+    }
+
+    #[test]
+    fn shfl_sync_down() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b32 d;
+                .reg .b32 v;
+                .reg .b32 m;
+                .reg .pred p;
+                shfl.sync.down.b32 d, v, 1, 0x1f, m;
+                shfl.sync.idx.b32 d|p, v, 0, 0x1f1f, m;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let body = func.body.as_ref().unwrap();
+        match &body[4] {
+            super::Statement::Instruction(None, super::Instruction::ShflSync { data, arguments }) => {
+                assert_eq!(data.mode, super::ShuffleMode::Down);
+                assert_eq!(arguments.dst_pred, None);
+                assert_eq!(
+                    arguments.src_opts,
+                    super::ParsedOperand::Imm(super::ImmediateValue::S64(0x1f))
+                );
+            }
+            _ => panic!(),
+        }
+        match &body[5] {
+            super::Statement::Instruction(None, super::Instruction::ShflSync { data, arguments }) => {
+                assert_eq!(data.mode, super::ShuffleMode::Idx);
+                assert_eq!(arguments.dst_pred, Some(super::ParsedOperand::Reg("p")));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn shfl_sync_clamp_out_of_range() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b32 d;
+                .reg .b32 v;
+                shfl.sync.bfly.b32 d, v, 1, 0x3f, 0xffffffff;
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_some());
+        assert!(matches!(
+            errors[..],
+            [super::PtxError::ShuffleClampOutOfRange { value: 0x3f }]
+        ));
+    }
+
+    #[test]
+    fn variable_byte_size() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b32 %r<4>;
+                .shared .b8 x[256];
+                .local .v2.f32 y[2][3];
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let sizes = func
+            .body
+            .as_ref()
+            .unwrap()
+            .iter()
+            .filter_map(|statement| match statement {
+                super::Statement::Variable(var) => Some(var.byte_size()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![16, 256, 48]);
+    }
+
+    #[test]
+    fn mov_function_address() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .func foo()
+            {
+                ret;
+            }
+
+            .entry foobar()
+            {
+                .reg .u64 r;
+                .reg .u64 s;
+                mov.u64 r, foo;
+                mov.u64 r, s;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[1] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let body = func.body.as_ref().unwrap();
+        let src_is_address = body[2..4]
+            .iter()
+            .map(|statement| match statement {
+                super::Statement::Instruction(None, super::Instruction::Mov { data, .. }) => {
+                    data.src_is_address
+                }
+                _ => panic!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(src_is_address, vec![true, false]);
+    }
+
+    #[test]
+    fn bar_arrive() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                bar.arrive 0, 128;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        match &func.body.as_ref().unwrap()[0] {
+            super::Statement::Instruction(None, super::Instruction::BarArrive { arguments, .. }) => {
+                assert_eq!(arguments.src1, super::ParsedOperand::Imm(super::ImmediateValue::S64(0)));
+                assert_eq!(
+                    arguments.src2,
+                    super::ParsedOperand::Imm(super::ImmediateValue::S64(128))
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn bar_arrive_missing_count() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                bar.arrive 0;
+                ret;
+            }";
+        assert!(super::parse_module_checked(text).is_err());
+    }
+
+    #[test]
+    fn instruction_min_arch() {
+        let text = "
+            .version 7.0
+            .target sm_80
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b32 r;
+                .reg .f32 f;
+                .reg .u64 a;
+                .reg .b128 wide;
+                mov.b32 r, 1;
+                shfl.sync.down.b32 r, r, 1, 0x1f, 0xffffffff;
+                ld.global.b128 wide, [a];
+                ld.relaxed.cluster.global.b32 r, [a];
+                atom.global.add.noftz.f16x2 r, [a], r;
+                cvt.rn.relu.f16x2.f32 r, f, f;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let archs = func
+            .body
+            .as_ref()
+            .unwrap()
+            .iter()
+            .filter_map(|statement| match statement {
+                super::Statement::Instruction(_, instruction) => Some(instruction.min_arch()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(archs, vec![10, 30, 70, 90, 60, 80, 10]);
+    }
+
+    #[test]
+    fn kernel_param_layout() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar(
+                .param .u32 a,
+                .param .u64 b,
+                .param .align 16 .b8 s[32]
+            )
+            {
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        assert_eq!(
+            super::param_layout(&func.func_directive),
+            vec![
+                ("a".to_string(), 0, 4),
+                ("b".to_string(), 8, 8),
+                ("s".to_string(), 16, 32)
+            ]
+        );
+    }
+
+    #[test]
+    fn param_slot_offset() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .func (.param .align 4 .b8 out[16]) foo()
+            {
+                .reg .b32 r;
+                mov.b32 r, 1;
+                st.param.b32 [out+8], r;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        match &func.body.as_ref().unwrap()[2] {
+            super::Statement::Instruction(None, super::Instruction::St { data, arguments }) => {
+                assert!(data.state_space == super::StateSpace::Param);
+                assert_eq!(arguments.src1, super::ParsedOperand::RegOffset("out", 8));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn normalize_declarations() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b32 %r2;
+                .reg .pred p;
+                .reg .b32 %r0;
+                .reg .b32 %r1;
+                .reg .b32 %r1;
+                add.u32 %r2, %r0, %r1;
+                ret;
+            }";
+        let mut module = super::parse_module_checked(text).unwrap();
+        let func = match &mut module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        super::normalize_declarations(func);
+        let body = func.body.as_ref().unwrap();
+        assert_eq!(body.len(), 4);
+        let mut declarations = body[..2]
+            .iter()
+            .map(|statement| match statement {
+                super::Statement::Variable(var) => (var.var.name, var.count),
+                _ => panic!(),
+            })
+            .collect::<Vec<_>>();
+        declarations.sort();
+        assert_eq!(declarations, vec![("%r", Some(3)), ("p", None)]);
+        match &body[2] {
+            super::Statement::Instruction(None, super::Instruction::Add { arguments, .. }) => {
+                assert_eq!(arguments.dst, super::ParsedOperand::Reg("%r2"));
+                assert_eq!(arguments.src1, super::ParsedOperand::Reg("%r0"));
+                assert_eq!(arguments.src2, super::ParsedOperand::Reg("%r1"));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn generic_memory_ops() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .u32 r;
+                .reg .u64 a;
+                ld.u32 r, [a];
+                ld.global.u32 r, [a];
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let body = func.body.as_ref().unwrap();
+        let generic = super::generic_memory_ops(func);
+        assert_eq!(generic.len(), 1);
+        assert!(std::ptr::eq(generic[0], &body[2]));
+    }
+
+    #[test]
+    fn mul_hi_u64() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .u64 d;
+                .reg .u64 a;
+                .reg .u64 b;
+                mul.hi.u64 d, a, b;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        match &func.body.as_ref().unwrap()[3] {
+            super::Statement::Instruction(None, super::Instruction::Mul { data, .. }) => {
+                assert!(matches!(
+                    data,
+                    super::MulDetails::Integer {
+                        control: super::MulIntControl::High,
+                        ..
+                    }
+                ));
+                assert!(data.dst_type() == super::ScalarType::U64);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn mul_hi_u64_wide_destination() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b128 d;
+                .reg .u64 a;
+                .reg .u64 b;
+                mul.hi.u64 d, a, b;
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn undeclared_guard() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                @undeclared bra L;
+            L:
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn write_body_layout() {
+        use std::fmt::Write;
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .pred p;
+                @p bra L;
+                {
+                    ret;
+                }
+            L:
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let mut result = String::new();
+        super::write_body(&mut result, func.body.as_ref().unwrap(), |w, statement| {
+            match statement {
+                super::Statement::Variable(var) => write!(w, ".reg .pred {}", var.var.name),
+                super::Statement::Instruction(_, super::Instruction::Bra { arguments, .. }) => {
+                    write!(w, "bra {}", arguments.src)
+                }
+                super::Statement::Instruction(_, super::Instruction::Ret { .. }) => {
+                    write!(w, "ret")
+                }
+                _ => panic!(),
+            }
+        })
+        .unwrap();
+        let expected = "{
+    .reg .pred p;
+    @p bra L;
+
+    {
+        ret;
+    }
+L:
+    ret;
+}
+";
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn array_initializer_zero_padded() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .b32 x[4] = {1, 2};";
+        let module = super::parse_module_checked(text).unwrap();
+        match &module.directives[0] {
+            super::Directive::Variable(_, var) => {
+                assert_eq!(var.array_init, [1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn array_initializer_too_many_elements() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .b32 x[2] = {1, 2, 3};";
+        let errors = super::parse_module(text).errors;
+        assert!(matches!(errors[..], [super::PtxError::ArrayInitalizer]));
+    }
+
+    #[test]
+    fn target_arch_rejects_newer_instructions() {
+        // `mma.sync` and `wgmma` are not parsed yet, the sm_80 bf16
+        // conversion stands in for them
+        let text = "
+            .version 7.8
+            .target sm_90
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .f32 a;
+                .reg .b16 d;
+                cvt.rn.bf16.f32 d, a;
+                ret;
+            }";
+        let options = super::ParseOptions {
+            target_arch: Some(60),
+            ..Default::default()
+        };
+        let super::ParseResult { module, errors, .. } =
+            super::parse_module_with_options(text, options);
+        assert!(module.is_none());
+        assert!(matches!(
+            errors[..],
+            [super::PtxError::UnsupportedArch {
+                required: 80,
+                target: 60
+            }]
+        ));
+        let options = super::ParseOptions {
+            target_arch: Some(80),
+            ..Default::default()
+        };
+        let super::ParseResult { module, errors, .. } =
+            super::parse_module_with_options(text, options);
+        assert!(module.is_some());
+        assert!(errors.is_empty());
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_some());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn cvt_ftz_f16_from_f32() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .f32 a;
+                .reg .b16 d;
+                cvt.rn.ftz.f16.f32 d, a;
+                cvt.rn.ftz.bf16.f32 d, a;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let body = match &module.directives[0] {
+            super::Directive::Method(_, func) => func.body.as_ref().unwrap(),
+            _ => panic!(),
+        };
+        for statement in &body[2..4] {
+            assert!(matches!(
+                statement,
+                super::Statement::Instruction(
+                    None,
+                    super::Instruction::Cvt {
+                        data: super::CvtDetails {
+                            mode: super::CvtMode::FPTruncate {
+                                flush_to_zero: Some(true),
+                                ..
+                            },
+                            ..
+                        },
+                        ..
+                    }
+                )
+            ));
+        }
+    }
+
+    #[test]
+    fn cvt_ftz_on_integers_rejected() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .s16 a;
+                .reg .s32 d;
+                cvt.ftz.s32.s16 d, a;
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_none());
+        assert!(matches!(errors[..], [super::PtxError::NonF32Ftz]));
+    }
+
+    #[test]
+    fn setp_bit_types() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .b32 a;
+                .reg .pred p;
+                setp.eq.b32 p, a, 1;
+                setp.ne.b32 p, a, 1;
+                ret;
+            }";
+        assert!(super::parse_module_checked(text).is_ok());
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .b32 a;
+                .reg .pred p;
+                setp.lt.b32 p, a, 1;
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_none());
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn reparse_function() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry first()
+            {
+                ret;
+            }
+
+            .visible .entry second()
+            {
+                .reg .u32 a;
+                add.u32 a, a, 1;
+                ret;
+            }";
+        let start = text.find(".visible .entry second").unwrap();
+        let func = super::reparse_function(text, start..text.len()).unwrap();
+        assert!(matches!(
+            func.func_directive.name,
+            super::MethodName::Kernel("second")
+        ));
+        assert_eq!(func.body.unwrap().len(), 3);
+        let end = text.rfind('}').unwrap();
+        assert!(super::reparse_function(text, start..end).is_err());
+        let errors = super::reparse_function(text, start..text.len() + 1).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::Lexer { .. }]));
+    }
+
+    #[test]
+    fn bar_spelling() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                bar.sync 0;
+                barrier.sync.aligned 0;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let body = match &module.directives[0] {
+            super::Directive::Method(_, func) => func.body.as_ref().unwrap(),
+            _ => panic!(),
+        };
+        fn bar<'a, 'input>(
+            statement: &'a super::Statement<super::ParsedOperandStr<'input>>,
+        ) -> (
+            bool,
+            super::BarSpelling,
+            &'a super::ParsedOperandStr<'input>,
+            &'a Option<super::ParsedOperandStr<'input>>,
+        ) {
+            match statement {
+                super::Statement::Instruction(
+                    None,
+                    super::Instruction::Bar {
+                        data: super::BarData { aligned, spelling },
+                        arguments,
+                    },
+                ) => (*aligned, *spelling, &arguments.src1, &arguments.src2),
+                _ => panic!(),
+            }
+        }
+        let (bar_aligned, bar_spelling, bar_src1, bar_src2) = bar(&body[0]);
+        let (barrier_aligned, barrier_spelling, barrier_src1, barrier_src2) = bar(&body[1]);
+        assert_eq!(bar_spelling, super::BarSpelling::Bar);
+        assert_eq!(barrier_spelling, super::BarSpelling::Barrier);
+        assert!(bar_aligned && barrier_aligned);
+        assert!(bar_src1 == barrier_src1 && bar_src2 == barrier_src2);
+        let printed = super::to_canonical_string(&module);
+        assert!(printed.contains("bar.sync 0;"));
+        assert!(printed.contains("barrier.sync.aligned 0;"));
+        let reparsed = super::parse_module_checked(&printed).unwrap();
+        assert_eq!(super::to_canonical_string(&reparsed), printed);
+    }
+
+    #[test]
+    fn infer_address_spaces() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar(.param .u64 input)
+            {
+                .reg .u64 in_addr;
+                .reg .u64 ptr;
+                .reg .u32 a;
+                ld.param.u64 in_addr, [input];
+                ld.u32 a, [in_addr];
+                cvta.to.global.u64 ptr, in_addr;
+                ld.u32 a, [ptr+4];
+                add.u64 ptr, ptr, 8;
+                st.u32 [ptr], a;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let inferred = super::infer_address_spaces(func);
+        assert_eq!(inferred.len(), 1);
+        let (statement, space) = &inferred[0];
+        assert!(*space == super::StateSpace::Global);
+        assert!(std::ptr::eq(*statement, &func.body.as_ref().unwrap()[6]));
+    }
+
+    #[test]
+    fn mov_width_mismatch() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .b32 d32;
+                .reg .b64 a64;
+                mov.b64 d32, a64;
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_none());
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn mov_type_pun() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .f32 f;
+                .reg .u32 i;
+                mov.b32 f, i;
+                mov.f32 f, i;
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_some());
+        assert!(matches!(errors[..], [super::PtxError::MovTypePun]));
+    }
+
+    #[test]
+    fn pragma_multiple_arguments() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .pragma \"nounroll\";
+                .pragma \"a\", \"b\";
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let body = match &module.directives[0] {
+            super::Directive::Method(_, func) => func.body.as_ref().unwrap(),
+            _ => panic!(),
+        };
+        match &body[..2] {
+            [super::Statement::Pragma(first), super::Statement::Pragma(second)] => {
+                assert_eq!(first, &["nounroll"]);
+                assert_eq!(second, &["a", "b"]);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn shfl_sync_missing_membermask() {
+        let text = "
+            .version 6.5
+            .target sm_70
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .b32 d;
+                .reg .b32 v;
+                shfl.sync.down.b32 d, v, 1, 0x1f;
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_none());
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn function_summary() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .func helper()
+            {
+                ret;
+            }
+
+            .visible .entry foobar(.param .u64 input)
+            {
+                .reg .u64 in_addr;
+                .reg .u32 a;
+                .reg .pred p;
+                ld.param.u64 in_addr, [input];
+                ld.u32 a, [in_addr];
+                setp.eq.u32 p, a, 0;
+                @p bra END;
+                {
+                    add.u32 a, a, 1;
+                    st.u32 [in_addr], a;
+                }
+            END:
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let kernels = module.entry_points().collect::<Vec<_>>();
+        assert_eq!(kernels.len(), 1);
+        let kernel = kernels[0];
+        assert_eq!(kernel.func_directive.name(), "foobar");
+        assert_eq!(kernel.statement_count(), 11);
+        assert_eq!(kernel.instruction_count(), 7);
+        assert_eq!(kernel.basic_block_count(), 3);
+    }
+
+    #[test]
+    fn ld_st_param_vector() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .func (.param .align 16 .b8 result[16]) foobar(.param .align 16 .b8 bigParam[16])
+            {
+                .reg .u64 lo;
+                .reg .u64 hi;
+                ld.param.v2.u64 {lo, hi}, [bigParam];
+                st.param.v2.u64 [result], {lo, hi};
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let body = match &module.directives[0] {
+            super::Directive::Method(_, func) => func.body.as_ref().unwrap(),
+            _ => panic!(),
+        };
+        match &body[2] {
+            super::Statement::Instruction(None, super::Instruction::Ld { data, arguments }) => {
+                assert!(data.state_space == super::StateSpace::Param);
+                assert!(data.typ == super::Type::Vector(2, super::ScalarType::U64));
+                assert!(matches!(
+                    &arguments.dst,
+                    super::ParsedOperand::VecPack(v) if v == &["lo", "hi"]
+                ));
+            }
+            _ => panic!(),
+        }
+        let text = text.replace(".align 16 .b8 bigParam", ".align 8 .b8 bigParam");
+        let super::ParseResult { module, errors, .. } = super::parse_module(&text);
+        assert!(module.is_some());
+        assert!(matches!(errors[..], [super::PtxError::MisalignedAccess]));
+    }
+
+    #[test]
+    fn tokens_with_spans() {
+        let tokens = super::tokens("add.u32 a, b;").unwrap();
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(tokens[2], (Token::Ident("a"), 8..9));
+        assert_eq!(super::tokens("add.u32 a, #b;").unwrap_err(), super::TokenError(11..12));
+    }
+
+    #[test]
+    fn predicate_in_arithmetic() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .s32 d;
+                .reg .s32 b;
+                .reg .pred pPred;
+                add.s32 d, pPred, b;
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_none());
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn lower_selp_to_branches() {
+        use std::fmt::Write;
+        let header = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            ";
+        let body = "
+            {
+                .reg .u32 d;
+                .reg .u32 a;
+                .reg .pred p;
+                selp.u32 d, a, 7, p;
+                ret;
+            }";
+        let text = format!("{header}{body}");
+        let mut module = super::parse_module_checked(&text).unwrap();
+        let func = match &mut module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        super::lower_selp_to_branches(func);
+        let mut lowered = String::new();
+        super::write_body(&mut lowered, func.body.as_ref().unwrap(), |w, statement| {
+            match statement {
+                super::Statement::Variable(var) => {
+                    let type_ = match var.var.v_type {
+                        super::Type::Scalar(super::ScalarType::U32) => "u32",
+                        super::Type::Scalar(super::ScalarType::Pred) => "pred",
+                        _ => panic!(),
+                    };
+                    write!(w, ".reg .{type_} {}", var.var.name)
+                }
+                super::Statement::Instruction(_, super::Instruction::Mov { data, arguments }) => {
+                    assert!(data.typ == super::Type::Scalar(super::ScalarType::U32));
+                    write!(w, "mov.u32 {}, {}", arguments.dst, arguments.src)
+                }
+                super::Statement::Instruction(_, super::Instruction::Ret { .. }) => {
+                    write!(w, "ret")
+                }
+                _ => panic!(),
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            lowered,
+            "{
+    .reg .u32 d;
+    .reg .u32 a;
+    .reg .pred p;
+    @p mov.u32 d, a;
+    @!p mov.u32 d, 7;
+    ret;
+}
+"
+        );
+        assert!(super::parse_module_checked(&format!("{header}{lowered}")).is_ok());
+    }
+
+    #[test]
+    fn ld_natural_alignment() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .shared .align 4 .b8 buffer[64];
+                .reg .u64 a;
+                .reg .u32 b;
+                ld.shared.u32 b, [buffer+4];
+                ld.shared.u64 a, [buffer];
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_some());
+        assert!(matches!(errors[..], [super::PtxError::MisalignedAccess]));
+    }
+
+    #[test]
+    fn max_threads() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry first()
+            .maxntid 1024
+            .reqntid 16, 16, 1
+            {
+                ret;
+            }
+
+            .entry second()
+            .maxntid 32, 4
+            {
+                ret;
+            }
+
+            .entry third()
+            {
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let threads = module
+            .entry_points()
+            .map(super::max_threads)
+            .collect::<Vec<_>>();
+        assert_eq!(threads, [Some(256), Some(128), None]);
+    }
+
+    #[test]
+    fn write_to_const_space() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u64 a;
+                .reg .u32 b;
+                ld.const.u32 b, [a];
+                {instruction}
+                ret;
+            }";
+        let parse =
+            |instruction| super::parse_module(&text.replace("{instruction}", instruction)).errors;
+        assert!(parse("").is_empty());
+        for instruction in [
+            "st.const.u32 [a], b;",
+            "atom.const.add.u32 b, [a], 1;",
+            "atom.const.cas.b32 b, [a], b, 1;",
+        ] {
+            assert!(matches!(parse(instruction)[..], [super::PtxError::SyntaxError]));
+        }
+    }
+
+    #[test]
+    fn remove_redundant_cvts() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .s32 a;
+                .reg .s32 b;
+                .reg .s64 t;
+                .reg .s64 u;
+                .reg .f32 f;
+                .reg .f64 g;
+                cvt.s64.s32 t, a;
+                cvt.s32.s64 b, t;
+                cvt.rn.f32.s32 f, b;
+                cvt.f64.f32 g, f;
+                cvt.rn.f32.f64 f, g;
+                add.f64 g, g, g;
+                cvt.s32.s64 a, u;
+                cvt.s64.s32 u, a;
+                ret;
+            }";
+        let mut module = super::parse_module_checked(text).unwrap();
+        let func = match &mut module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        super::remove_redundant_cvts(func);
+        let body = &func.body.as_ref().unwrap()[6..];
+        assert_eq!(body.len(), 8);
+        // s32 -> s64 -> s32 is lossless and `t` is dead
+        match &body[0] {
+            super::Statement::Instruction(None, super::Instruction::Mov { data, arguments }) => {
+                assert!(data.typ == super::Type::Scalar(super::ScalarType::S32));
+                assert_eq!(arguments.dst, super::ParsedOperand::Reg("b"));
+                assert_eq!(arguments.src, super::ParsedOperand::Reg("a"));
+            }
+            _ => panic!(),
+        }
+        assert!(matches!(body[1], super::Statement::Instruction(_, super::Instruction::Cvt { .. })));
+        // f32 -> f64 -> f32 is lossless, but `g` is still read
+        assert!(matches!(body[2], super::Statement::Instruction(_, super::Instruction::Cvt { .. })));
+        assert!(matches!(body[3], super::Statement::Instruction(_, super::Instruction::Mov { .. })));
+        // s64 -> s32 -> s64 is lossy
+        assert!(matches!(body[5], super::Statement::Instruction(_, super::Instruction::Cvt { .. })));
+        assert!(matches!(body[6], super::Statement::Instruction(_, super::Instruction::Cvt { .. })));
+    }
+
+    #[test]
+    fn divergent_uniform_branch() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u32 tid;
+                .reg .u32 x;
+                .reg .pred pTid;
+                .reg .pred pUniform;
+                mov.u32 tid, %tid.x;
+                add.u32 x, tid, 1;
+                setp.eq.u32 pTid, x, 0;
+                setp.eq.u32 pUniform, 1, 0;
+                @pUniform bra.uni L;
+                @pTid bra L;
+                @pTid bra.uni L;
+            L:
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_some());
+        assert!(matches!(errors[..], [super::PtxError::DivergentUniformBranch]));
+    }
+
+    #[test]
+    fn vector_element_types() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u64 a;
+                .reg .f32 x;
+                .reg .f32 y;
+                .reg .b32 z;
+                .reg .{w_type} w;
+                st.global.v4.f32 [a], {x, y, z, w};
+                ret;
+            }";
+        let parse = |w_type| super::parse_module(&text.replace("{w_type}", w_type)).errors;
+        assert!(parse("f32").is_empty());
+        assert!(matches!(parse("u32")[..], [super::PtxError::SyntaxError]));
+        assert!(matches!(parse("f64")[..], [super::PtxError::SyntaxError]));
+        let text = text.replace(".f32", ".u16").replace("v4.u16", "v4.u8");
+        let parse = |w_type| super::parse_module(&text.replace("{w_type}", w_type)).errors;
+        assert!(parse("u32").is_empty());
+        assert!(matches!(parse("f32")[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn immediate_out_of_type_range() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u16 d;
+                .reg .u16 a;
+                {instruction}
+                ret;
+            }";
+        let parse =
+            |instruction| super::parse_module(&text.replace("{instruction}", instruction)).errors;
+        assert!(parse("add.u16 d, a, 65535;").is_empty());
+        assert!(parse("add.u16 d, a, -1;").is_empty());
+        assert!(parse("add.s16 d, a, -32768;").is_empty());
+        match &parse("add.u16 d, a, 70000;")[..] {
+            [super::PtxError::LiteralOutOfRange {
+                value,
+                expected_type: "u16",
+            }] => assert_eq!(value, "70000"),
+            _ => panic!(),
+        }
+        assert!(matches!(
+            parse("add.s16 d, a, -32769;")[..],
+            [super::PtxError::LiteralOutOfRange { .. }]
+        ));
+        assert!(parse("mad.lo.u16 d, a, 200, 65535;").is_empty());
+    }
+
+    #[test]
+    fn eliminate_dead_regs() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u32 a;
+                .reg .u32 unused;
+                .pragma \"used\";
+                .reg .u32 kept;
+                .reg .pred p;
+                .reg .u32 %r<4>;
+                @p mov.u32 a, 1;
+                ret;
+            }";
+        let mut module = super::parse_module_checked(text).unwrap();
+        let func = match &mut module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        super::eliminate_dead_regs(func);
+        let names = func
+            .body
+            .as_ref()
+            .unwrap()
+            .iter()
+            .filter_map(|statement| match statement {
+                super::Statement::Variable(var) => Some(var.var.name),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "kept", "p", "%r"]);
+    }
+
+    #[test]
+    fn symbol_plus_offset() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .u32 myArr[8];
+
+            .visible .entry foobar()
+            {
+                .reg .u64 r;
+                .reg .u32 v;
+                mov.u64 r, myArr+16;
+                mov.u64 r, r+16;
+                ld.global.u32 v, [myArr+16];
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let body = match &module.directives[1] {
+            super::Directive::Method(_, func) => func.body.as_ref().unwrap(),
+            _ => panic!(),
+        };
+        match &body[2] {
+            super::Statement::Instruction(_, super::Instruction::Mov { arguments, .. }) => {
+                assert_eq!(arguments.src, super::ParsedOperand::SymbolOffset("myArr", 16));
+                assert_eq!(arguments.src.to_string(), "myArr+16");
+            }
+            _ => panic!(),
+        }
+        match &body[3] {
+            super::Statement::Instruction(_, super::Instruction::Mov { arguments, .. }) => {
+                assert_eq!(arguments.src, super::ParsedOperand::RegOffset("r", 16));
+            }
+            _ => panic!(),
+        }
+        match &body[4] {
+            super::Statement::Instruction(_, super::Instruction::Ld { arguments, .. }) => {
+                assert_eq!(arguments.src, super::ParsedOperand::RegOffset("myArr", 16));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn canonical_string() {
+        let first = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            // Spelled with optional modifiers and alternative mnemonics
+            .visible .entry foobar(.param .u64 input)
+            .maxntid 64
+            {
+                .reg .u64 in_addr;
+                .reg .u32 a;
+                .reg .pred p;
+                ld.param.u64 in_addr, [input];
+                ld.weak.global.ca.u32 a, [in_addr];
+                setp.lo.u32 p, a, 0x10;
+                @p atom.relaxed.gpu.global.add.u32 a, [in_addr], 1;
+                bar.sync 0;
+                ret;
+            }";
+        let second = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+            .visible .entry foobar(
+                .param .u64 input
+            ) .maxntid 64, 1, 1 {
+                .reg .u64   in_addr;
+                .reg .u32   a;
+                .reg .pred  p;
+                ld.param.u64    in_addr, [input];
+                ld.global.u32   a, [in_addr];
+                setp.lt.u32     p, a, 16;
+                @p atom.global.add.u32  a, [in_addr], 1;
+                bar.sync    0;
+                ret;
+            }";
+        let first = super::to_canonical_string(&super::parse_module_checked(first).unwrap());
+        let second = super::to_canonical_string(&super::parse_module_checked(second).unwrap());
+        assert_eq!(first, second);
+        assert!(first.contains(".visible .entry foobar(.param .u64 input)\n.maxntid 64, 1, 1\n{"));
+        assert!(first.contains("    @p atom.global.add.u32 a, [in_addr], 1;\n"));
+        // The canonical form is valid PTX and is its own canonical form
+        let reparsed = super::parse_module_checked(&first).unwrap();
+        assert_eq!(super::to_canonical_string(&reparsed), first);
+    }
+
+    #[test]
+    fn func_return_space() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .func (.global .b32 r) foo ()
+            {
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_none());
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .func (.reg .b32 r) foo ()
+            {
+                mov.b32 r, 1;
+                ret;
+            }";
+        assert!(super::parse_module_checked(text).is_ok());
+    }
+
+    #[test]
+    fn atom_cas_b64() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar(.param .u64 a)
+            {
+                .reg .b64 d;
+                .reg .b64 cmp;
+                .reg .b64 val;
+                .reg .b32 narrow;
+                atom.global.cas.b64 d, [a], cmp, val;
+                atom.global.cas.b64 d, [a], narrow, val;
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn atom_cas_b128() {
+        let text = "
+            .version 8.3
+            .target sm_90
+            .address_size 64
+
+            .entry foobar(.param .u64 a)
+            {
+                .reg .b128 d;
+                .reg .b128 cmp;
+                .reg .b64 lo;
+                .reg .b64 hi;
+                atom.global.cas.b128 d, [a], cmp, {lo, hi};
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let instructions = match &module.directives[0] {
+            super::Directive::Method(_, func) => func.body.as_ref().unwrap(),
+            _ => panic!(),
+        };
+        assert!(matches!(
+            instructions[4],
+            super::Statement::Instruction(
+                _,
+                super::Instruction::AtomCas {
+                    data: super::ast::AtomCasDetails {
+                        type_: super::ScalarType::B128,
+                        ..
+                    },
+                    arguments: super::ast::AtomCasArgs {
+                        src3: super::ast::ParsedOperand::VecPack(_),
+                        ..
+                    }
+                }
+            )
+        ));
+        let text = "
+            .version 8.3
+            .target sm_90
+            .address_size 64
+
+            .entry foobar(.param .u64 a)
+            {
+                .reg .b128 d;
+                .reg .b32 lo;
+                .reg .b32 hi;
+                atom.global.cas.b128 d, [a], {lo, hi}, d;
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn atom_inc_dec_u32_only() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar(.param .u64 a)
+            {
+                .reg .u32 d;
+                .reg .u32 mod;
+                atom.global.inc.u32 d, [a], mod;
+                atom.global.dec.u32 d, [a], mod;
+                ret;
+            }";
+        assert!(super::parse_module_checked(text).is_ok());
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar(.param .u64 a)
+            {
+                .reg .s32 d;
+                .reg .s32 mod;
+                atom.global.inc.s32 d, [a], mod;
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn vector_of_packed_bf16x2() {
+        let text = "
+            .version 7.8
+            .target sm_90
+            .address_size 64
+
+            .global .align 8 .b8 buf[16];
+
+            .entry foobar(.param .u64 a)
+            {
+                .reg .b32 lo;
+                .reg .b32 hi;
+                st.global.v2.bf16x2 [a], {lo, hi};
+                st.global.v2.bf16x2 [buf+4], {lo, hi};
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(matches!(errors[..], [super::PtxError::MisalignedAccess]));
+        let module = module.unwrap();
+        let instructions = match &module.directives[1] {
+            super::Directive::Method(_, func) => func.body.as_ref().unwrap(),
+            _ => panic!(),
+        };
+        match &instructions[2] {
+            super::Statement::Instruction(_, super::Instruction::St { data, .. }) => {
+                assert!(data.typ == super::Type::Vector(2, super::ScalarType::BF16x2));
+                assert_eq!(data.typ.size_of_bytes(), 8);
+            }
+            _ => panic!(),
+        }
+        let text = "
+            .version 7.8
+            .target sm_90
+            .address_size 64
+
+            .entry foobar(.param .u64 a)
+            {
+                .reg .b32 lo;
+                .reg .b32 hi;
+                .reg .b32 x;
+                .reg .b32 y;
+                st.global.v2.bf16x2 [a], {lo, hi, x, y};
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn guarded_uniform_branch() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .pred %p1;
+                setp.eq.u32 %p1, 1, 1;
+                @%p1 bra.uni BB2;
+            BB2:
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let instructions = match &module.directives[0] {
+            super::Directive::Method(_, func) => func.body.as_ref().unwrap(),
+            _ => panic!(),
+        };
+        assert!(matches!(
+            instructions[2],
+            super::Statement::Instruction(
+                Some(super::PredAt { not: false, label: "%p1" }),
+                super::Instruction::Bra {
+                    data: super::BraData { uniform: true },
+                    arguments: super::BraArgs { src: "BB2" }
+                }
+            )
+        ));
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b32 %r1;
+                @%r1 bra.uni BB2;
+            BB2:
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+    }
+
+    #[test]
+    fn statement_list_edits() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .u32 a;
+                .reg .u32 b;
+                add.u32 a, a, 1;
+                {
+                    sub.u32 b, b, 1;
+                }
+                ret;
+            }";
+        let mut module = super::parse_module_checked(text).unwrap();
+        let func = match &mut module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let mut body = func.statement_list().unwrap();
+        let add = body
+            .iter()
+            .position(|statement| {
+                matches!(statement, super::Statement::Instruction(_, super::Instruction::Add { .. }))
+            })
+            .unwrap();
+        let mov = super::Statement::Instruction(
+            None,
+            super::Instruction::Mov {
+                data: super::MovDetails::new(None, super::ScalarType::U32),
+                arguments: super::MovArgs {
+                    dst: super::ParsedOperand::Reg("b"),
+                    src: super::ParsedOperand::Reg("a"),
+                },
+            },
+        );
+        body.insert_after(add, [mov]);
+        let mut block = body.block(add + 2).unwrap();
+        let sub = block.replace(0, []);
+        assert!(block.is_empty());
+        block.insert(0, [sub]);
+        let printed = super::to_canonical_string(&module);
+        let reparsed = super::parse_module_checked(&printed).unwrap();
+        let body = match &reparsed.directives[0] {
+            super::Directive::Method(_, func) => func.body.as_ref().unwrap(),
+            _ => panic!(),
+        };
+        assert!(matches!(
+            body[3],
+            super::Statement::Instruction(
+                None,
+                super::Instruction::Mov {
+                    arguments: super::MovArgs {
+                        dst: super::ParsedOperand::Reg("b"),
+                        src: super::ParsedOperand::Reg("a"),
+                    },
+                    ..
+                }
+            )
+        ));
+        assert!(matches!(&body[4], super::Statement::Block(block) if block.len() == 1));
+        assert_eq!(super::to_canonical_string(&reparsed), printed);
+    }
+
+    #[test]
+    fn half_initializer_round_trip() {
+        let text = "
+            .version 7.8
+            .target sm_90
+            .address_size 64
+
+            .global .f16 h[4] = {0f3F800000, 0fC0200000, 0f33800000, 0f3F801000};
+            .global .bf16 b = 0f3FC00000;";
+        let module = super::parse_module_checked(text).unwrap();
+        let array_init = |module: &super::Module, index: usize| match &module.directives[index] {
+            super::Directive::Variable(_, var) => var.array_init.clone(),
+            _ => panic!(),
+        };
+        // The last value is halfway between two halves and rounds to even
+        assert_eq!(
+            array_init(&module, 0),
+            [0x00, 0x3c, 0x00, 0xc1, 0x01, 0x00, 0x00, 0x3c]
+        );
+        assert_eq!(array_init(&module, 1), [0xc0, 0x3f]);
+        let printed = super::to_canonical_string(&module);
+        assert!(printed.contains("{0f3F800000, 0fC0200000, 0f33800000, 0f3F800000}"));
+        assert!(printed.contains("= 0f3FC00000"));
+        let reparsed = super::parse_module_checked(&printed).unwrap();
+        assert_eq!(array_init(&reparsed, 0), array_init(&module, 0));
+        assert_eq!(array_init(&reparsed, 1), array_init(&module, 1));
+    }
+
+    #[test]
+    fn cvt_ftz_pack() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b32 d;
+                .reg .f32 a;
+                .reg .f32 b;
+                cvt.rn.ftz.f16x2.f32 d, a, b;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let instruction = match &func.body.as_ref().unwrap()[3] {
+            super::Statement::Instruction(None, instruction) => instruction,
+            _ => panic!(),
+        };
+        match instruction {
+            super::Instruction::CvtPack { data, .. } => {
+                assert!(data.to == super::ScalarType::F16x2);
+                assert!(data.flush_to_zero);
+                assert!(!data.relu);
+            }
+            _ => panic!(),
+        }
+        let mut printed = String::new();
+        super::write_instruction(&mut printed, instruction).unwrap();
+        assert_eq!(printed, "cvt.rn.ftz.f16x2.f32 d, a, b");
+        // The packed form always takes two sources
+        let one_source = text.replace("d, a, b;", "d, a;");
+        assert!(super::parse_module_checked(&one_source).is_err());
+    }
+
+    #[test]
+    fn link_extern_declaration() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .extern .func (.reg .f64 ret) __nv_sin (.reg .f64 x);
+
+            .visible .entry foobar()
+            {
+                .reg .f64 x;
+                .reg .f64 y;
+                call (y), __nv_sin, (x);
+                ret;
+            }";
+        let libdevice = "
+            .version 7.0
+            .target sm_50
+            .address_size 64
+
+            .visible .func (.reg .f64 ret) __nv_sin (.reg .f64 x)
+            {
+                mov.f64 ret, x;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        match &module.directives[0] {
+            super::Directive::Method(linking, func) => {
+                assert!(linking.contains(super::LinkingDirective::EXTERN));
+                assert_eq!(func.func_directive.name(), "__nv_sin");
+                assert!(func.body.is_none());
+            }
+            _ => panic!(),
+        }
+        let library = super::parse_module_checked(libdevice).unwrap();
+        let mut names = Vec::new();
+        let linked = super::link(module, library, &mut names).unwrap();
+        assert_eq!(linked.version, (7, 0));
+        assert_eq!(linked.target, (50, None));
+        let names = linked
+            .directives
+            .iter()
+            .map(|directive| match directive {
+                super::Directive::Method(_, func) => {
+                    (func.func_directive.name(), func.body.is_some())
+                }
+                _ => panic!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, [("__nv_sin", true), ("foobar", true)]);
+        let printed = super::to_canonical_string(&linked);
+        assert!(super::parse_module_checked(&printed).is_ok());
+        let module = super::parse_module_checked(text).unwrap();
+        let wrong_type = libdevice
+            .replace("(.reg .f64 x)", "(.reg .f32 x)")
+            .replace("mov.f64 ret, x", "cvt.f64.f32 ret, x");
+        let library = super::parse_module_checked(&wrong_type).unwrap();
+        assert!(matches!(
+            super::link(module, library, &mut Vec::new()),
+            Err(super::PtxError::SignatureMismatch { name }) if name == "__nv_sin"
+        ));
+    }
+
+    #[test]
+    fn link_linkage() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .extern .func (.reg .f64 ret) lib_fn (.reg .f64 x);
+            .extern .func hidden ();
+
+            .func (.reg .f64 ret) helper (.reg .f64 x)
+            {
+                mov.f64 ret, x;
+                ret;
+            }
+
+            .visible .func overridable ()
+            {
+                ret;
+            }
+
+            .visible .entry foobar()
+            {
+                .reg .f64 x;
+                call (x), helper, (x);
+                call (x), lib_fn, (x);
+                call hidden;
+                ret;
+            }";
+        let library = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .func (.reg .f64 ret) helper (.reg .f64 x)
+            {
+                add.f64 ret, x, x;
+                ret;
+            }
+
+            .func hidden ()
+            {
+                ret;
+            }
+
+            .weak .func overridable ()
+            {
+                ret;
+            }
+
+            .visible .func (.reg .f64 ret) lib_fn (.reg .f64 x)
+            {
+                call (ret), helper, (x);
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let library = super::parse_module_checked(library).unwrap();
+        let mut names = Vec::new();
+        let linked = super::link(module, library, &mut names).unwrap();
+        let functions = linked
+            .directives
+            .iter()
+            .map(|directive| match directive {
+                super::Directive::Method(_, func) => func,
+                _ => panic!(),
+            })
+            .collect::<Vec<_>>();
+        let signatures = functions
+            .iter()
+            .map(|func| (func.func_directive.name(), func.body.is_some()))
+            .collect::<Vec<_>>();
+        // File-local helpers are renamed instead of conflicting, a file-local
+        // definition does not resolve `.extern hidden` and the `.weak`
+        // definition loses to the `.visible` one
+        assert_eq!(
+            signatures,
+            [
+                ("helper$1", true),
+                ("hidden$1", true),
+                ("lib_fn", true),
+                ("hidden", false),
+                ("helper", true),
+                ("overridable", true),
+                ("foobar", true),
+            ]
+        );
+        let calls_helper = |func: &super::Function<_, _>, helper| {
+            func.body.as_ref().unwrap().iter().any(|statement| {
+                matches!(
+                    statement,
+                    super::Statement::Instruction(
+                        _,
+                        super::Instruction::Call { arguments, .. }
+                    ) if arguments.func == helper
+                )
+            })
+        };
+        assert!(calls_helper(functions[2], "helper$1"));
+        assert!(calls_helper(functions[6], "helper"));
+        let printed = super::to_canonical_string(&linked);
+        assert!(super::parse_module_checked(&printed).is_ok());
+    }
+
+    #[test]
+    fn divergent_barrier() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u32 tid;
+                .reg .pred pDivergent;
+                .reg .pred pUniform;
+                mov.u32 tid, %tid.x;
+                setp.eq.u32 pDivergent, tid, 0;
+                setp.eq.u32 pUniform, 1, 0;
+                @pUniform bar.sync 0;
+                @pDivergent bar.sync 0;
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_some());
+        assert!(matches!(errors[..], [super::PtxError::DivergentBarrier]));
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u32 tid;
+                .reg .pred pDivergent;
+                mov.u32 tid, %tid.x;
+                setp.eq.u32 pDivergent, tid, 0;
+            LOOP:
+                bar.sync 0;
+                @pDivergent bra SKIP;
+                bar.sync 0;
+            SKIP:
+                bar.sync 0;
+                @pDivergent bra LOOP;
+                ret;
+            }";
+        let super::ParseResult { module, errors, .. } = super::parse_module(text);
+        assert!(module.is_some());
+        assert!(matches!(errors[..], [super::PtxError::DivergentBarrier]));
+    }
+
+    #[test]
+    fn address_register_width() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .u32 a32;
+                .reg .u64 a64;
+                .reg .u32 x;
+                ld.global.u32 x, [a64];
+                ld.shared.u32 x, [a32];
+                ld.global.u32 x, [a32+4];
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 32
+
+            .entry foobar()
+            {
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::Unsupported32Bit]));
+    }
+
+    #[test]
+    fn multi_source_operands() {
+        let text = "
+            .version 6.5
+            .target sm_70
+            .address_size 64
+
+            .entry foobar()
+            {
+                .reg .b32 d;
+                .reg .b32 a;
+                .reg .b32 b;
+                .reg .b32 c;
+                .reg .f32 f;
+                lop3.b32 d, a, b, c, 0x96;
+                shf.l.wrap.b32 d, a, b, c;
+                shf.r.clamp.b32 d, a, b, c;
+                sad.u32 d, a, b, c;
+                prmt.b32 d, a, b, c;
+                bfi.b32 d, a, b, c, 8;
+                mad.lo.u32 d, a, b, c;
+                fma.rn.f32 f, f, f, f;
+                ret;
+            }";
+        let module = super::parse_module_checked(text).unwrap();
+        let func = match &module.directives[0] {
+            super::Directive::Method(_, func) => func,
+            _ => panic!(),
+        };
+        let instructions = func
+            .body
+            .as_ref()
+            .unwrap()
+            .iter()
+            .filter_map(|statement| match statement {
+                super::Statement::Instruction(_, instruction) => Some(instruction),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        match instructions[0] {
+            super::Instruction::Lop3 { data, .. } => assert_eq!(*data, 0x96),
+            _ => panic!(),
+        }
+        assert_eq!(instructions[0].reads(), ["a", "b", "c"]);
+        assert_eq!(instructions[0].writes(), ["d"]);
+        assert_eq!(
+            instructions[0].operands(),
+            [
+                (super::ParsedOperand::Reg("d"), true),
+                (super::ParsedOperand::Reg("a"), false),
+                (super::ParsedOperand::Reg("b"), false),
+                (super::ParsedOperand::Reg("c"), false),
+            ]
+        );
+        match instructions[2] {
+            super::Instruction::Shf { data, .. } => {
+                assert_eq!(data.direction, super::ShiftDirection::Right);
+                assert_eq!(data.mode, super::VideoShiftMode::Clamp);
+            }
+            _ => panic!(),
+        }
+        for instruction in &instructions[1..7] {
+            assert_eq!(instruction.reads(), ["a", "b", "c"]);
+            assert_eq!(instruction.writes(), ["d"]);
+        }
+        assert_eq!(instructions[7].reads(), ["f", "f", "f"]);
+        assert_eq!(instructions[7].writes(), ["f"]);
+        let printed = super::to_canonical_string(&module);
+        assert!(printed.contains("lop3.b32 d, a, b, c, 150;"));
+        assert!(printed.contains("shf.l.wrap.b32 d, a, b, c;"));
+        let reparsed = super::parse_module_checked(&printed).unwrap();
+        assert_eq!(super::to_canonical_string(&reparsed), printed);
+    }
+
+    #[test]
+    fn parse_result() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .global .align 4 .b8 buf[64];
+
+            .entry foobar()
+            {
+                .reg .b128 r;
+                ld.global.b128 r, [buf+16];
+                ret;
+            }";
+        let result = super::parse_module(text);
+        assert!(!result.has_fatal_errors());
+        assert!(matches!(
+            result.errors[..],
+            [super::PtxError::MisalignedAccess]
+        ));
+        assert_eq!(result.token_count, super::tokens(text).unwrap().len());
+        let module = result.into_result().unwrap();
+        assert_eq!(module.directives.len(), 2);
+        let invalid = text.replace("ret;", "@undeclared ret;");
+        let result = super::parse_module(&invalid);
+        assert!(result.has_fatal_errors());
+        assert!(result.module.is_none());
+        assert!(result.token_count > 0);
+        let errors = result.into_result().err().unwrap();
+        assert!(errors.iter().any(|err| !err.is_warning()));
+        let result = super::parse_module(".version 6.5 ~");
+        assert!(format!("{:?}", result).contains("has_module: false"));
+        assert!(result.has_fatal_errors());
+        assert_eq!(result.token_count, 0);
+        assert!(matches!(
+            result.into_result().err().unwrap()[..],
+            [super::PtxError::Lexer { .. }]
+        ));
+    }
+
+    #[test]
+    fn bfe_immediate_in_range() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u32 a;
+                .reg .u32 d;
+                bfe.u32 d, a, 4, 8;
+                ret;
+            }";
+        assert!(super::parse_module_checked(text).is_ok());
+    }
+
+    #[test]
+    fn bfe_immediate_out_of_range() {
+        let text = "
+            .version 6.5
+            .target sm_30
+            .address_size 64
+
+            .visible .entry foobar()
+            {
+                .reg .u32 a;
+                .reg .u32 d;
+                bfe.u32 d, a, 40, 8;
+                ret;
+            }";
+        let errors = super::parse_module_checked(text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+        let text = text.replace("40, 8", "32, 1");
+        let errors = super::parse_module_checked(&text).err().unwrap();
+        assert!(matches!(errors[..], [super::PtxError::SyntaxError]));
+        let text = text.replace("32, 1", "31, 32");
+        assert!(super::parse_module_checked(&text).is_ok());
+    }
 }
@@ -0,0 +1,987 @@
+use crate::ast::*;
+use crate::{PtxError, StateSpace};
+use std::collections::{HashMap, HashSet};
+
+// Simple passes that work directly on the parsed AST. They are meant for
+// tools that consume PTX without going through the full compiler pipeline
+
+// `mov.type r, r;` is a no-op, regardless of the type or the predicate guard
+pub fn remove_self_moves<ID, Ident: Copy + PartialEq>(
+    func: &mut Function<'_, ID, Statement<ParsedOperand<Ident>>>,
+) {
+    if let Some(ref mut body) = func.body {
+        remove_self_moves_impl(body);
+    }
+}
+
+fn remove_self_moves_impl<Ident: Copy + PartialEq>(
+    body: &mut Vec<Statement<ParsedOperand<Ident>>>,
+) {
+    body.retain_mut(|statement| match statement {
+        Statement::Instruction(_, Instruction::Mov { arguments, .. }) => {
+            arguments.dst != arguments.src
+        }
+        Statement::Block(block) => {
+            remove_self_moves_impl(block);
+            true
+        }
+        _ => true,
+    });
+}
+
+// Renames the registers and parameters declared in this function, both their
+// declarations and every reference to them. Other names, e.g. labels, called
+// functions and module-level variables, are left untouched, as are names
+// missing from the map. The new names are borrowed from `map`, so it has to
+// outlive the function
+pub fn rename_registers<'a>(
+    func: &mut Function<'a, &'a str, Statement<ParsedOperand<&'a str>>>,
+    map: &'a HashMap<&str, String>,
+) {
+    let mut declared = HashSet::new();
+    for var in func
+        .func_directive
+        .return_arguments
+        .iter()
+        .chain(func.func_directive.input_arguments.iter())
+    {
+        declared.insert(var.name);
+    }
+    if let Some(ref body) = func.body {
+        declared_registers(body, &mut declared);
+    }
+    let rename = |name: &mut &'a str| {
+        if !declared.contains(name) {
+            return;
+        }
+        if let Some(new_name) = map.get(name) {
+            *name = new_name.as_str();
+        }
+    };
+    for var in func
+        .func_directive
+        .return_arguments
+        .iter_mut()
+        .chain(func.func_directive.input_arguments.iter_mut())
+    {
+        rename(&mut var.name);
+    }
+    if let Some(ref mut body) = func.body {
+        rename_registers_impl(body, &rename);
+    }
+}
+
+fn declared_registers<'a>(
+    body: &[Statement<ParsedOperand<&'a str>>],
+    declared: &mut HashSet<&'a str>,
+) {
+    for statement in body {
+        match statement {
+            Statement::Variable(var)
+                if matches!(var.var.state_space, StateSpace::Reg | StateSpace::Param) =>
+            {
+                declared.insert(var.var.name);
+            }
+            Statement::Block(block) => declared_registers(block, declared),
+            _ => {}
+        }
+    }
+}
+
+fn rename_registers_impl<'a>(
+    body: &mut [Statement<ParsedOperand<&'a str>>],
+    rename: &impl Fn(&mut &'a str),
+) {
+    for statement in body.iter_mut() {
+        match statement {
+            Statement::Label(_) | Statement::Pragma(_) => {}
+            Statement::Variable(var) => rename(&mut var.var.name),
+            Statement::Instruction(guard, instruction) => {
+                if let Some(guard) = guard {
+                    rename(&mut guard.label);
+                }
+                instruction.for_each_operand_mut(|operand| match operand {
+                    ParsedOperand::Reg(name)
+                    | ParsedOperand::RegOffset(name, _)
+                    | ParsedOperand::SymbolOffset(name, _)
+                    | ParsedOperand::VecMember(name, _)
+                    | ParsedOperand::Generic(name) => rename(name),
+                    ParsedOperand::VecPack(names) => names.iter_mut().for_each(rename),
+                    ParsedOperand::Imm(_) | ParsedOperand::SpecialRegisterDimension(..) => {}
+                });
+            }
+            Statement::Block(block) => rename_registers_impl(block, rename),
+        }
+    }
+}
+
+// A `call` together with its ABI lowering: the `st.param` writes of the input
+// arguments before the call and the `ld.param` reads of the return values
+// after it
+pub struct CallSite<'f, T: Operand> {
+    pub call: &'f CallArgs<T>,
+    pub setup: Vec<&'f Instruction<T>>,
+    pub teardown: Vec<&'f Instruction<T>>,
+}
+
+// Setup is searched for between the previous call and this one, teardown
+// between this call and the next one. Nested blocks are searched separately
+pub fn call_sequences<'f, ID, Ident: Copy + PartialEq>(
+    func: &'f Function<'_, ID, Statement<ParsedOperand<Ident>>>,
+) -> Vec<CallSite<'f, ParsedOperand<Ident>>> {
+    let mut result = Vec::new();
+    if let Some(ref body) = func.body {
+        call_sequences_impl(body, &mut result);
+    }
+    result
+}
+
+fn call_sequences_impl<'f, Ident: Copy + PartialEq>(
+    body: &'f [Statement<ParsedOperand<Ident>>],
+    result: &mut Vec<CallSite<'f, ParsedOperand<Ident>>>,
+) {
+    fn param_name<Ident: Copy>(operand: &ParsedOperand<Ident>) -> Option<Ident> {
+        match operand {
+            ParsedOperand::Reg(name) | ParsedOperand::RegOffset(name, _) => Some(*name),
+            _ => None,
+        }
+    }
+    fn call_args<T: Operand>(statement: &Statement<T>) -> Option<&CallArgs<T>> {
+        match statement {
+            Statement::Instruction(_, Instruction::Call { arguments, .. }) => Some(arguments),
+            _ => None,
+        }
+    }
+    let mut previous_call = 0;
+    for (index, statement) in body.iter().enumerate() {
+        let call = match statement {
+            Statement::Block(block) => {
+                call_sequences_impl(block, result);
+                continue;
+            }
+            statement => match call_args(statement) {
+                Some(call) => call,
+                None => continue,
+            },
+        };
+        let next_call = body[index + 1..]
+            .iter()
+            .position(|statement| call_args(statement).is_some())
+            .map_or(body.len(), |offset| index + 1 + offset);
+        let setup = body[previous_call..index]
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Instruction(_, instruction @ Instruction::St { data, arguments })
+                    if data.state_space == StateSpace::Param
+                        && param_name(&arguments.src1).is_some_and(|name| {
+                            call.input_arguments
+                                .iter()
+                                .any(|arg| param_name(arg) == Some(name))
+                        }) =>
+                {
+                    Some(instruction)
+                }
+                _ => None,
+            })
+            .collect();
+        let teardown = body[index + 1..next_call]
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Instruction(_, instruction @ Instruction::Ld { data, arguments })
+                    if data.state_space == StateSpace::Param
+                        && param_name(&arguments.src)
+                            .is_some_and(|name| call.return_arguments.contains(&name)) =>
+                {
+                    Some(instruction)
+                }
+                _ => None,
+            })
+            .collect();
+        result.push(CallSite {
+            call,
+            setup,
+            teardown,
+        });
+        previous_call = index + 1;
+    }
+}
+
+// Offset and size of each input parameter in the parameter bank. Parameters
+// are laid out in declaration order, each aligned to its `.align` or to the
+// natural alignment of its element type
+pub fn param_layout<ID: ToString>(decl: &MethodDeclaration<'_, ID>) -> Vec<(String, u64, u64)> {
+    let mut offset = 0u64;
+    decl.input_arguments
+        .iter()
+        .map(|param| {
+            let align = param
+                .align
+                .unwrap_or_else(|| crate::type_size(&param.v_type)) as u64;
+            offset = offset.div_ceil(align) * align;
+            let size = param.v_type.size_of_bytes();
+            let layout = (param.name.to_string(), offset, size);
+            offset += size;
+            layout
+        })
+        .collect()
+}
+
+// Threads per block allowed by the tuning directives. `.reqntid` gives the
+// exact block size, so it wins over the `.maxntid` upper bound
+pub fn max_threads<ID, S>(func: &Function<'_, ID, S>) -> Option<u32> {
+    let reqntid = func.tuning.iter().find_map(|directive| match *directive {
+        TuningDirective::ReqNtid(nx, ny, nz) => Some((nx, ny, nz)),
+        _ => None,
+    });
+    let maxntid = func.tuning.iter().find_map(|directive| match *directive {
+        TuningDirective::MaxNtid(nx, ny, nz) => Some((nx, ny, nz)),
+        _ => None,
+    });
+    let (nx, ny, nz) = reqntid.or(maxntid)?;
+    nx.checked_mul(ny)?.checked_mul(nz)
+}
+
+// Merges `.reg` declarations of numbered registers (`%r0`, `%r1`, `%r<2>`)
+// into a single counted range when together they cover `%r0` up to `%rN`,
+// drops duplicates and moves register declarations to the start of their
+// block, ordered by type. Register names are left untouched
+pub fn normalize_declarations<'a, ID>(
+    func: &mut Function<'a, ID, Statement<ParsedOperand<&'a str>>>,
+) {
+    if let Some(ref mut body) = func.body {
+        normalize_declarations_impl(body);
+    }
+}
+
+struct RegisterGroup<'a> {
+    base: &'a str,
+    numbered: bool,
+    declarations: Vec<MultiVariable<&'a str>>,
+}
+
+fn normalize_declarations_impl<'a>(body: &mut Vec<Statement<ParsedOperand<&'a str>>>) {
+    let mut groups = Vec::<RegisterGroup<'a>>::new();
+    let mut rest = Vec::with_capacity(body.len());
+    for statement in body.drain(..) {
+        let var = match statement {
+            Statement::Variable(var)
+                if var.var.state_space == StateSpace::Reg
+                    && !matches!(var.var.v_type, Type::Array(..)) =>
+            {
+                var
+            }
+            Statement::Block(mut block) => {
+                normalize_declarations_impl(&mut block);
+                rest.push(Statement::Block(block));
+                continue;
+            }
+            statement => {
+                rest.push(statement);
+                continue;
+            }
+        };
+        let (base, numbered) = match (var.count, register_index(var.var.name)) {
+            (Some(_), _) => (var.var.name, true),
+            (None, Some((prefix, _))) => (prefix, true),
+            (None, None) => (var.var.name, false),
+        };
+        let group = groups.iter_mut().find(|group| {
+            group.base == base
+                && group.numbered == numbered
+                && group.declarations[0].var.v_type == var.var.v_type
+                && group.declarations[0].var.align == var.var.align
+        });
+        match group {
+            Some(group) => {
+                let duplicate = group
+                    .declarations
+                    .iter()
+                    .any(|decl| decl.var.name == var.var.name && decl.count == var.count);
+                if !duplicate {
+                    group.declarations.push(var);
+                }
+            }
+            None => groups.push(RegisterGroup {
+                base,
+                numbered,
+                declarations: vec![var],
+            }),
+        }
+    }
+    groups.sort_by(|a, b| {
+        type_order(&a.declarations[0].var.v_type)
+            .cmp(&type_order(&b.declarations[0].var.v_type))
+            .then(a.base.cmp(b.base))
+    });
+    for group in groups {
+        let mut declarations = group.declarations;
+        if group.numbered {
+            if let Some(count) = contiguous_count(&declarations) {
+                let mut var = declarations.swap_remove(0);
+                var.var.name = group.base;
+                var.count = Some(count);
+                declarations = vec![var];
+            }
+        }
+        body.extend(declarations.into_iter().map(Statement::Variable));
+    }
+    body.extend(rest);
+}
+
+// `%r12` is register 12 of the `%r` range. Leading zeros (`%r01`) would be
+// lost when merging, so they are not treated as numbered
+fn register_index(name: &str) -> Option<(&str, u32)> {
+    let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    let digits = &name[prefix.len()..];
+    if prefix.is_empty() || digits.is_empty() || (digits.len() > 1 && digits.starts_with('0')) {
+        return None;
+    }
+    Some((prefix, digits.parse().ok()?))
+}
+
+// Number of registers if the declarations cover exactly `0..count`
+fn contiguous_count(declarations: &[MultiVariable<&str>]) -> Option<u32> {
+    let mut indices = Vec::new();
+    for decl in declarations {
+        match decl.count {
+            Some(count) => indices.extend(0..count),
+            None => indices.push(register_index(decl.var.name)?.1),
+        }
+    }
+    indices.sort_unstable();
+    indices.dedup();
+    let count = indices.len() as u32;
+    if indices.last().is_some_and(|last| *last + 1 == count) {
+        Some(count)
+    } else {
+        None
+    }
+}
+
+fn type_order(type_: &Type) -> (u8, u8, u8) {
+    match type_ {
+        Type::Scalar(scalar) => (0, *scalar as u8, 0),
+        Type::Vector(len, scalar) => (1, *scalar as u8, *len),
+        Type::Array(..) | Type::Pointer(..) => (2, 0, 0),
+    }
+}
+
+// `ld`/`st` without a state space go through a generic address, the backend
+// has to resolve the actual space (`cvta`, `isspacep`) before the access
+pub fn generic_memory_ops<'f, ID, T: Operand>(
+    func: &'f Function<'_, ID, Statement<T>>,
+) -> Vec<&'f Statement<T>> {
+    let mut result = Vec::new();
+    if let Some(ref body) = func.body {
+        generic_memory_ops_impl(body, &mut result);
+    }
+    result
+}
+
+fn generic_memory_ops_impl<'f, T: Operand>(
+    body: &'f [Statement<T>],
+    result: &mut Vec<&'f Statement<T>>,
+) {
+    for statement in body {
+        match statement {
+            Statement::Instruction(_, Instruction::Ld { data, .. })
+                if data.state_space == StateSpace::Generic =>
+            {
+                result.push(statement)
+            }
+            Statement::Instruction(_, Instruction::St { data, .. })
+                if data.state_space == StateSpace::Generic =>
+            {
+                result.push(statement)
+            }
+            Statement::Block(block) => generic_memory_ops_impl(block, result),
+            _ => {}
+        }
+    }
+}
+
+// Generic `ld`/`st` whose address was produced by `cvta.to.global` or
+// `cvta.to.shared`, together with that space. Only straight-line code is
+// followed: a label or a nested block forgets everything known so far, and so
+// does any other write to the address register
+pub fn infer_address_spaces<'f, ID, Ident: Copy + PartialEq>(
+    func: &'f Function<'_, ID, Statement<ParsedOperand<Ident>>>,
+) -> Vec<(&'f Statement<ParsedOperand<Ident>>, StateSpace)> {
+    let mut result = Vec::new();
+    if let Some(ref body) = func.body {
+        infer_address_spaces_impl(body, &mut result);
+    }
+    result
+}
+
+fn infer_address_spaces_impl<'f, Ident: Copy + PartialEq>(
+    body: &'f [Statement<ParsedOperand<Ident>>],
+    result: &mut Vec<(&'f Statement<ParsedOperand<Ident>>, StateSpace)>,
+) {
+    fn address<Ident: Copy>(operand: &ParsedOperand<Ident>) -> Option<Ident> {
+        match operand {
+            ParsedOperand::Reg(name) | ParsedOperand::RegOffset(name, _) => Some(*name),
+            _ => None,
+        }
+    }
+    let mut known = Vec::<(Ident, StateSpace)>::new();
+    for statement in body {
+        let instruction = match statement {
+            Statement::Instruction(_, instruction) => instruction,
+            Statement::Block(block) => {
+                infer_address_spaces_impl(block, result);
+                known.clear();
+                continue;
+            }
+            Statement::Label(_) => {
+                known.clear();
+                continue;
+            }
+            Statement::Variable(_) | Statement::Pragma(_) => continue,
+        };
+        let generic_address = match instruction {
+            Instruction::Ld { data, arguments } if data.state_space == StateSpace::Generic => {
+                address(&arguments.src)
+            }
+            Instruction::St { data, arguments } if data.state_space == StateSpace::Generic => {
+                address(&arguments.src1)
+            }
+            _ => None,
+        };
+        if let Some(name) = generic_address {
+            if let Some((_, space)) = known.iter().find(|(known, _)| *known == name) {
+                result.push((statement, *space));
+            }
+        }
+        let _ = visit(
+            instruction,
+            &mut |operand: &ParsedOperand<Ident>,
+                  _: Option<(&Type, StateSpace)>,
+                  is_dst: bool,
+                  _: bool| {
+                if let (true, Some(name)) = (is_dst, address(operand)) {
+                    known.retain(|(known, _)| *known != name);
+                }
+                Ok::<_, std::convert::Infallible>(())
+            },
+        );
+        if let Instruction::Cvta {
+            data:
+                CvtaDetails {
+                    state_space: space @ (StateSpace::Global | StateSpace::Shared),
+                    direction: CvtaDirection::GenericToExplicit,
+                },
+            arguments,
+        } = instruction
+        {
+            if let Some(name) = address(&arguments.dst) {
+                known.push((name, *space));
+            }
+        }
+    }
+}
+
+// Rewrites `selp.type d, a, b, p;` into `@p mov.type d, a; @!p mov.type d, b;`
+// for backends without a native select. No branches are needed, so no labels
+// are created. `selp` with its own guard is left untouched, the two
+// predicates can't be combined in a single guard
+pub fn lower_selp_to_branches<ID, Ident: Copy>(
+    func: &mut Function<'_, ID, Statement<ParsedOperand<Ident>>>,
+) {
+    if let Some(ref mut body) = func.body {
+        lower_selp_to_branches_impl(body);
+    }
+}
+
+fn lower_selp_to_branches_impl<Ident: Copy>(body: &mut Vec<Statement<ParsedOperand<Ident>>>) {
+    let mut result = Vec::with_capacity(body.len());
+    for statement in body.drain(..) {
+        match statement {
+            Statement::Instruction(None, Instruction::Selp { data, arguments }) => {
+                let predicate = match arguments.src3 {
+                    ParsedOperand::Reg(predicate) => predicate,
+                    src3 => {
+                        result.push(Statement::Instruction(
+                            None,
+                            Instruction::Selp {
+                                data,
+                                arguments: SelpArgs { src3, ..arguments },
+                            },
+                        ));
+                        continue;
+                    }
+                };
+                for (not, src) in [(false, arguments.src1), (true, arguments.src2)] {
+                    result.push(Statement::Instruction(
+                        Some(PredAt {
+                            not,
+                            label: predicate,
+                        }),
+                        Instruction::Mov {
+                            data: MovDetails::new(None, data),
+                            arguments: MovArgs {
+                                dst: arguments.dst.clone(),
+                                src,
+                            },
+                        },
+                    ));
+                }
+            }
+            Statement::Block(mut block) => {
+                lower_selp_to_branches_impl(&mut block);
+                result.push(Statement::Block(block));
+            }
+            statement => result.push(statement),
+        }
+    }
+    *body = result;
+}
+
+// Replaces `cvt.B.A t, a; cvt.A.B d, t;` with `mov.A d, a;` when the first
+// conversion is exact (integer or float widening), so the round trip gives
+// back `a`. The first `cvt` is kept if `t` is read anywhere else
+pub fn remove_redundant_cvts<Ident: Copy + PartialEq>(
+    func: &mut Function<'_, Ident, Statement<ParsedOperand<Ident>>>,
+) {
+    let body = match func.body {
+        Some(ref mut body) => body,
+        None => return,
+    };
+    let mut temporaries = Vec::new();
+    replace_cvt_round_trips(body, &mut temporaries);
+    if temporaries.is_empty() {
+        return;
+    }
+    let mut reads = Vec::new();
+    collect_reads(body, &mut reads);
+    temporaries.retain(|temp| {
+        !reads.contains(temp)
+            && !func
+                .func_directive
+                .return_arguments
+                .iter()
+                .any(|arg| arg.name == *temp)
+    });
+    remove_dead_cvts(body, &temporaries);
+}
+
+fn replace_cvt_round_trips<Ident: Copy + PartialEq>(
+    body: &mut [Statement<ParsedOperand<Ident>>],
+    temporaries: &mut Vec<Ident>,
+) {
+    for index in 0..body.len() {
+        if let Statement::Block(block) = &mut body[index] {
+            replace_cvt_round_trips(block, temporaries);
+            continue;
+        }
+        fn as_cvt<T: Operand>(statement: &Statement<T>) -> Option<(&CvtDetails, &CvtArgs<T>)> {
+            match statement {
+                Statement::Instruction(None, Instruction::Cvt { data, arguments }) => {
+                    Some((data, arguments))
+                }
+                _ => None,
+            }
+        }
+        let (first, first_args) = match as_cvt(&body[index]) {
+            Some(cvt) => cvt,
+            None => continue,
+        };
+        let (second, second_args) = match body.get(index + 1).and_then(as_cvt) {
+            Some(cvt) => cvt,
+            None => continue,
+        };
+        let temp = match (&first_args.dst, &second_args.src) {
+            (ParsedOperand::Reg(dst), ParsedOperand::Reg(src)) if dst == src => *dst,
+            _ => continue,
+        };
+        if !is_exact_cvt(first) || second.from != first.to || second.to != first.from {
+            continue;
+        }
+        let mov = Instruction::Mov {
+            data: MovDetails::new(None, first.from),
+            arguments: MovArgs {
+                dst: second_args.dst.clone(),
+                src: first_args.src.clone(),
+            },
+        };
+        body[index + 1] = Statement::Instruction(None, mov);
+        temporaries.push(temp);
+    }
+}
+
+fn is_exact_cvt(cvt: &CvtDetails) -> bool {
+    match cvt.mode {
+        CvtMode::ZeroExtend | CvtMode::SignExtend => !cvt.saturate,
+        CvtMode::FPExtend { flush_to_zero } => flush_to_zero != Some(true),
+        _ => false,
+    }
+}
+
+fn collect_reads<Ident: Copy + PartialEq>(
+    body: &[Statement<ParsedOperand<Ident>>],
+    reads: &mut Vec<Ident>,
+) {
+    for statement in body {
+        match statement {
+            Statement::Instruction(guard, instruction) => {
+                reads.extend(guard.as_ref().map(|guard| guard.label));
+                reads.extend(instruction.reads());
+            }
+            Statement::Block(block) => collect_reads(block, reads),
+            Statement::Label(_) | Statement::Variable(_) | Statement::Pragma(_) => {}
+        }
+    }
+}
+
+fn remove_dead_cvts<Ident: Copy + PartialEq>(
+    body: &mut Vec<Statement<ParsedOperand<Ident>>>,
+    temporaries: &[Ident],
+) {
+    body.retain_mut(|statement| match statement {
+        Statement::Instruction(None, Instruction::Cvt { arguments, .. }) => {
+            !matches!(arguments.dst, ParsedOperand::Reg(dst) if temporaries.contains(&dst))
+        }
+        Statement::Block(block) => {
+            remove_dead_cvts(block, temporaries);
+            true
+        }
+        _ => true,
+    });
+}
+
+// Removes `.reg` declarations that no instruction or guard refers to. A
+// declaration directly preceded by `.pragma "used";` is always kept
+pub fn eliminate_dead_regs<ID, Ident: Copy + PartialEq>(
+    func: &mut Function<'_, ID, Statement<ParsedOperand<Ident>>>,
+) {
+    let body = match func.body {
+        Some(ref mut body) => body,
+        None => return,
+    };
+    let mut used = Vec::new();
+    collect_used(body, &mut used);
+    eliminate_dead_regs_impl(body, &used);
+}
+
+fn collect_used<Ident: Copy>(body: &[Statement<ParsedOperand<Ident>>], used: &mut Vec<Ident>) {
+    for statement in body {
+        match statement {
+            Statement::Instruction(guard, instruction) => {
+                used.extend(guard.as_ref().map(|guard| guard.label));
+                let _ = visit(
+                    instruction,
+                    &mut |operand: &ParsedOperand<Ident>,
+                          _: Option<(&Type, StateSpace)>,
+                          _: bool,
+                          _: bool| {
+                        match operand {
+                            ParsedOperand::Reg(name)
+                            | ParsedOperand::RegOffset(name, _)
+                            | ParsedOperand::SymbolOffset(name, _)
+                            | ParsedOperand::VecMember(name, _)
+                            | ParsedOperand::SpecialRegisterDimension(name, _)
+                            | ParsedOperand::Generic(name) => used.push(*name),
+                            ParsedOperand::VecPack(names) => used.extend(names),
+                            ParsedOperand::Imm(_) => {}
+                        }
+                        Ok::<_, std::convert::Infallible>(())
+                    },
+                );
+            }
+            Statement::Block(block) => collect_used(block, used),
+            Statement::Label(_) | Statement::Variable(_) | Statement::Pragma(_) => {}
+        }
+    }
+}
+
+fn eliminate_dead_regs_impl<Ident: Copy + PartialEq>(
+    body: &mut Vec<Statement<ParsedOperand<Ident>>>,
+    used: &[Ident],
+) {
+    let mut marked_used = false;
+    body.retain_mut(|statement| {
+        let after_pragma = std::mem::replace(&mut marked_used, false);
+        match statement {
+            Statement::Pragma(args) => {
+                marked_used = args.iter().any(|arg| arg == "used");
+                true
+            }
+            // Numbered registers (`%r<10>`) are referred to by their
+            // individual names, which are not tracked
+            Statement::Variable(var)
+                if var.var.state_space == StateSpace::Reg && var.count.is_none() =>
+            {
+                after_pragma || used.contains(&var.var.name)
+            }
+            Statement::Block(block) => {
+                eliminate_dead_regs_impl(block, used);
+                true
+            }
+            _ => true,
+        }
+    });
+}
+
+// Links `module` with `library` (e.g. libdevice). Only `.visible` and
+// `.weak` definitions are exported. Declarations without a body on either
+// side are resolved against the exported definitions on the other side and
+// dropped. Two exported definitions of the same name are an error unless one
+// of them is `.weak`, then the other one wins. File-local definitions never
+// conflict: when their name is taken on the other side, they are renamed and
+// the new names are stored in `names`. Library directives come first, so that
+// its definitions precede their uses in `module`
+pub fn link<'input>(
+    mut module: Module<'input>,
+    mut library: Module<'input>,
+    names: &'input mut Vec<String>,
+) -> Result<Module<'input>, PtxError> {
+    let mut drop_from_module = Vec::new();
+    let mut drop_from_library = Vec::new();
+    for directive in module.directives.iter() {
+        let name = match exported(directive) {
+            Some(name) => name,
+            None => continue,
+        };
+        for other in library.directives.iter() {
+            if exported(other) == Some(name) {
+                match (is_weak(directive), is_weak(other)) {
+                    (false, false) => {
+                        return Err(PtxError::DuplicateDefinition {
+                            name: name.to_string(),
+                        })
+                    }
+                    (true, false) => drop_from_module.push(name),
+                    (_, true) => drop_from_library.push(name),
+                }
+            }
+        }
+    }
+    let resolve = |declarations: &[Directive<'input, ParsedOperand<&'input str>>],
+                   definitions: &[Directive<'input, ParsedOperand<&'input str>>],
+                   resolved: &mut Vec<&'input str>| {
+        for directive in declarations {
+            let name = match declaration(directive) {
+                Some(name) => name,
+                None => continue,
+            };
+            let defined = match definitions
+                .iter()
+                .find(|other| exported(other) == Some(name))
+            {
+                Some(defined) => defined,
+                None => continue,
+            };
+            if !same_signature(directive, defined) {
+                return Err(PtxError::SignatureMismatch {
+                    name: name.to_string(),
+                });
+            }
+            resolved.push(name);
+        }
+        Ok(())
+    };
+    let mut resolved_in_module = Vec::new();
+    let mut resolved_in_library = Vec::new();
+    resolve(
+        &module.directives,
+        &library.directives,
+        &mut resolved_in_module,
+    )?;
+    resolve(
+        &library.directives,
+        &module.directives,
+        &mut resolved_in_library,
+    )?;
+    rename_private_clashes(&mut module.directives, &mut library.directives, names);
+    let keep = |directive: &Directive<'input, ParsedOperand<&'input str>>,
+                dropped: &[&str],
+                resolved: &[&str]| {
+        !exported(directive).is_some_and(|name| dropped.contains(&name))
+            && !declaration(directive).is_some_and(|name| resolved.contains(&name))
+    };
+    let directives = library
+        .directives
+        .into_iter()
+        .filter(|directive| keep(directive, &drop_from_library, &resolved_in_library))
+        .chain(
+            module
+                .directives
+                .into_iter()
+                .filter(|directive| keep(directive, &drop_from_module, &resolved_in_module)),
+        )
+        .collect();
+    Ok(Module {
+        version: module.version.max(library.version),
+        target: module.target.max(library.target),
+        directives,
+    })
+}
+
+// Library file-local definitions are renamed when the module uses their name
+// in any way. Module ones only when the name stays taken in the library, by
+// an exported definition or a declaration
+fn rename_private_clashes<'input>(
+    module: &mut [Directive<'input, ParsedOperand<&'input str>>],
+    library: &mut [Directive<'input, ParsedOperand<&'input str>>],
+    names: &'input mut Vec<String>,
+) {
+    let module_names = module.iter().filter_map(symbol).collect::<Vec<_>>();
+    let library_names = library.iter().filter_map(symbol).collect::<Vec<_>>();
+    let library_private = library
+        .iter()
+        .filter_map(private)
+        .filter(|name| module_names.contains(name))
+        .collect::<Vec<_>>();
+    let module_private = module
+        .iter()
+        .filter_map(private)
+        .filter(|name| library_names.contains(name) && !library_private.contains(name))
+        .collect::<Vec<_>>();
+    let first = names.len();
+    for name in library_private.iter().chain(module_private.iter()) {
+        let new_name = (1..)
+            .map(|index| format!("{name}${index}"))
+            .find(|new_name| {
+                !module_names.contains(&new_name.as_str())
+                    && !library_names.contains(&new_name.as_str())
+            })
+            .unwrap();
+        names.push(new_name);
+    }
+    let names: &'input Vec<String> = names;
+    let mut new_names = names[first..].iter().map(String::as_str);
+    let library_renames = library_private
+        .iter()
+        .map(|name| (*name, new_names.next().unwrap()))
+        .collect::<HashMap<_, _>>();
+    let module_renames = module_private
+        .iter()
+        .map(|name| (*name, new_names.next().unwrap()))
+        .collect::<HashMap<_, _>>();
+    rename_symbols(library, &library_renames);
+    rename_symbols(module, &module_renames);
+}
+
+fn rename_symbols<'input>(
+    directives: &mut [Directive<'input, ParsedOperand<&'input str>>],
+    renames: &HashMap<&str, &'input str>,
+) {
+    if renames.is_empty() {
+        return;
+    }
+    let rename = |name: &mut &'input str| {
+        if let Some(new_name) = renames.get(name) {
+            *name = new_name;
+        }
+    };
+    for directive in directives {
+        match directive {
+            Directive::Variable(_, var) => rename(&mut var.name),
+            Directive::Method(_, func) => {
+                let declaration = &mut func.func_directive;
+                match declaration.name {
+                    MethodName::Kernel(ref mut name) | MethodName::Func(ref mut name) => {
+                        rename(name)
+                    }
+                }
+                for var in declaration
+                    .return_arguments
+                    .iter_mut()
+                    .chain(declaration.input_arguments.iter_mut())
+                {
+                    rename(&mut var.name);
+                }
+                if let Some(ref mut body) = func.body {
+                    rename_registers_impl(body, &rename);
+                }
+            }
+            Directive::DwarfLine(_) => {}
+        }
+    }
+}
+
+fn definition<'input>(
+    directive: &Directive<'input, ParsedOperand<&'input str>>,
+) -> Option<&'input str> {
+    match directive {
+        Directive::Method(_, func) if func.body.is_some() => Some(func.func_directive.name()),
+        Directive::Variable(linking, var) if !linking.contains(LinkingDirective::EXTERN) => {
+            Some(var.name)
+        }
+        _ => None,
+    }
+}
+
+fn exported<'input>(
+    directive: &Directive<'input, ParsedOperand<&'input str>>,
+) -> Option<&'input str> {
+    match directive {
+        Directive::Method(linking, _) | Directive::Variable(linking, _)
+            if linking.intersects(LinkingDirective::VISIBLE | LinkingDirective::WEAK) =>
+        {
+            definition(directive)
+        }
+        _ => None,
+    }
+}
+
+fn private<'input>(
+    directive: &Directive<'input, ParsedOperand<&'input str>>,
+) -> Option<&'input str> {
+    match exported(directive) {
+        Some(_) => None,
+        None => definition(directive),
+    }
+}
+
+fn declaration<'input>(
+    directive: &Directive<'input, ParsedOperand<&'input str>>,
+) -> Option<&'input str> {
+    match directive {
+        Directive::Method(_, func) if func.body.is_none() => Some(func.func_directive.name()),
+        Directive::Variable(linking, var) if linking.contains(LinkingDirective::EXTERN) => {
+            Some(var.name)
+        }
+        _ => None,
+    }
+}
+
+fn symbol<'input>(
+    directive: &Directive<'input, ParsedOperand<&'input str>>,
+) -> Option<&'input str> {
+    definition(directive).or_else(|| declaration(directive))
+}
+
+fn is_weak(directive: &Directive<'_, ParsedOperand<&str>>) -> bool {
+    match directive {
+        Directive::Method(linking, _) | Directive::Variable(linking, _) => {
+            linking.contains(LinkingDirective::WEAK)
+        }
+        Directive::DwarfLine(_) => false,
+    }
+}
+
+fn same_signature(
+    declaration: &Directive<'_, ParsedOperand<&str>>,
+    definition: &Directive<'_, ParsedOperand<&str>>,
+) -> bool {
+    let same_variables = |a: &[Variable<&str>], b: &[Variable<&str>]| {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(a, b)| a.v_type == b.v_type && a.state_space == b.state_space)
+    };
+    match (declaration, definition) {
+        (Directive::Method(_, declaration), Directive::Method(_, definition)) => {
+            let (declaration, definition) =
+                (&declaration.func_directive, &definition.func_directive);
+            same_variables(&declaration.return_arguments, &definition.return_arguments)
+                && same_variables(&declaration.input_arguments, &definition.input_arguments)
+        }
+        (Directive::Variable(_, declaration), Directive::Variable(_, definition)) => {
+            declaration.v_type == definition.v_type
+                && declaration.state_space == definition.state_space
+        }
+        _ => false,
+    }
+}
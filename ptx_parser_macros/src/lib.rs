@@ -326,12 +326,28 @@ impl SingleOpcodeDefinition {
                 break;
             }
         }
+        // Modifiers sharing a type can't be told apart when parsed out of order (e.g.
+        // `vshl.dtype.atype.btype.mode`), so everything starting from the first repeated type is
+        // parsed in order
+        if let Some(first_repeated) = Self::first_repeated_type(unordered_modifiers) {
+            result.extend(unordered_modifiers.drain(first_repeated..).rev());
+        }
         if unordered_modifiers.len() == 1 {
             result.push(unordered_modifiers.pop().unwrap());
         }
         result.reverse();
         result
     }
+
+    fn first_repeated_type(modifiers: &[DotModifierRef]) -> Option<usize> {
+        let mut seen = FxHashSet::default();
+        modifiers.iter().position(|modifier| match modifier {
+            DotModifierRef::Indirect { value, .. } => {
+                !seen.insert(value.type_.to_token_stream().to_string())
+            }
+            DotModifierRef::Direct { .. } => false,
+        })
+    }
 }
 
 fn gather_rules(
@@ -783,14 +799,18 @@ fn emit_definition_parser(
         };
         let can_be_negated = if arg.can_be_negated {
             quote! {
-                opt(any.verify(|t| *t == #token_type::Not)).map(|o| o.is_some())
+                opt(any.verify(|t| *t == #token_type::Exclamation)).map(|o| o.is_some())
             }
         } else {
             quote! {
                 empty
             }
         };
-        let operand = {
+        let operand = if arg.pre_bracket {
+            quote! {
+                ParsedOperandStr::parse_address
+            }
+        } else {
             quote! {
                 ParsedOperandStr::parse
             }